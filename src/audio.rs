@@ -0,0 +1,131 @@
+use crate::*;
+
+/// Semitones of the major pentatonic scale, which sounds fine in any order.
+const SCALE: [f32; 5] = [0.0, 2.0, 4.0, 7.0, 9.0];
+/// The pitch ladder stops climbing after two octaves.
+const LADDER_LENGTH: usize = SCALE.len() * 2;
+
+/// Whether each peg type's sound climbs the pitch ladder.
+/// The samples are `peg.<type>` in the asset manifest.
+const PEG_SOUNDS: [(PegType, bool); 6] = [
+    (PegType::GrowablePlant, false),
+    (PegType::Plant, true),
+    (PegType::Gold, true),
+    (PegType::Stone, false),
+    (PegType::MultiBall, false),
+    (PegType::Multiplier, false),
+];
+
+const CROSSFADE_SECONDS: f32 = 1.0;
+/// Roughly the impulse of a full power shot hitting a peg. Harder hits play at full volume.
+const HARD_HIT_IMPULSE: f32 = 800.0;
+/// Even a graze is heard.
+const MIN_HIT_VOLUME: f32 = 0.3;
+
+/// Playback speed for the `combo`th peg hit of a shot, one note up the scale per hit.
+pub fn ladder_speed(combo: usize) -> f32 {
+    let step = combo.saturating_sub(1).min(LADDER_LENGTH);
+    let octave = (step / SCALE.len()) as f32;
+    let semitones = octave * 12.0 + SCALE[step % SCALE.len()];
+    2.0_f32.powf(semitones / 12.0)
+}
+
+struct MusicTrack {
+    playing: LoopingSound,
+    /// From 0 (silent) to 1.
+    fade: f32,
+}
+
+pub struct Audio {
+    peg_sounds: Vec<(PegType, Handle<Sound>, bool)>,
+    board_music: Option<MusicTrack>,
+    shop_music: Option<MusicTrack>,
+}
+
+impl Audio {
+    /// Music is optional: tracks that aren't in the manifest are skipped.
+    pub fn load(resources: &Resources) -> Self {
+        let mut audio = Self {
+            peg_sounds: Vec::new(),
+            board_music: None,
+            shop_music: None,
+        };
+        audio.load_peg_sounds(resources);
+
+        let manifest = resources.get::<AssetManifest>();
+        let mut sounds = resources.get::<AssetStore<Sound>>();
+        let mut audio_manager = resources.get::<AudioManager>();
+        let mut start_music = |name: &str, fade: f32| -> Option<MusicTrack> {
+            let path = manifest.sound_path(name)?;
+            let sound = sounds.load(path, Default::default());
+            let playing = audio_manager.play_looping(sounds.get(&sound), 0.0);
+            Some(MusicTrack { playing, fade })
+        };
+
+        audio.board_music = start_music("music.board", 1.0);
+        audio.shop_music = start_music("music.shop", 0.0);
+        audio
+    }
+
+    /// Also used to pick up changes to the manifest while the game runs.
+    pub fn load_peg_sounds(&mut self, resources: &Resources) {
+        let manifest = resources.get::<AssetManifest>();
+        let mut sounds = resources.get::<AssetStore<Sound>>();
+        self.peg_sounds = PEG_SOUNDS
+            .iter()
+            .filter_map(|(peg_type, ladder)| {
+                let name = format!("peg.{}", peg_asset_name(*peg_type));
+                let path = match manifest.sound_path(&name) {
+                    Some(path) => path,
+                    None => {
+                        println!("No sound named {:?} in {:?}", name, MANIFEST_PATH);
+                        return None;
+                    }
+                };
+                let sound = sounds.load(path, Default::default());
+                Some((*peg_type, sound, *ladder))
+            })
+            .collect();
+    }
+
+    /// Softer hits, with a smaller `impulse`, play quieter.
+    pub fn play_peg_hit(
+        &self,
+        resources: &Resources,
+        peg_type: PegType,
+        combo: usize,
+        impulse: f32,
+    ) {
+        let sounds = resources.get::<AssetStore<Sound>>();
+        let mut audio_manager = resources.get::<AudioManager>();
+        let strength = (impulse / HARD_HIT_IMPULSE).clamp(MIN_HIT_VOLUME, 1.0);
+        let volume = resources.get::<Settings>().effective_sfx_volume() * strength;
+
+        if let Some((_, sound, ladder)) = self.peg_sounds.iter().find(|s| s.0 == peg_type) {
+            let speed = if *ladder { ladder_speed(combo) } else { 1.0 };
+            audio_manager.play_one_shot_with_speed_and_volume(sounds.get(sound), speed, volume);
+        }
+    }
+
+    /// Crossfades to the shop's music while in the shop and back on the board.
+    pub fn run_music(&mut self, resources: &Resources, time_step: f32) {
+        let in_shop = resources.get::<LevelState>().in_shop;
+        let volume = resources.get::<Settings>().effective_music_volume();
+        let mut audio_manager = resources.get::<AudioManager>();
+
+        let step = time_step / CROSSFADE_SECONDS;
+        for (track, target) in [
+            (&mut self.board_music, !in_shop),
+            (&mut self.shop_music, in_shop),
+        ] {
+            if let Some(track) = track {
+                track.fade = if target {
+                    (track.fade + step).min(1.0)
+                } else {
+                    (track.fade - step).max(0.0)
+                };
+                audio_manager.set_volume(&track.playing, track.fade * volume);
+            }
+        }
+    }
+}
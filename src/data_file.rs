@@ -0,0 +1,107 @@
+use crate::*;
+
+/// Reads a data file from disk so it can be edited without recompiling.
+/// On the web (or if the file is missing) the copy embedded at compile time is used.
+pub fn read_data_file(path: &str, embedded: &'static str) -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => return contents,
+            Err(e) => println!("Could not read {:?}, using the built-in copy: {:?}", path, e),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = path;
+
+    embedded.to_string()
+}
+
+/// Whether `path` exists on disk. Always false on the web, where only embedded copies are used.
+pub fn data_file_exists(path: &str) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::path::Path::new(path).exists()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Writes a data file relative to the working directory.
+/// Does nothing on the web.
+pub fn write_data_file(path: &str, contents: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Err(e) = std::fs::write(path, contents) {
+            println!("Could not write {:?}: {:?}", path, e);
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = (path, contents);
+}
+
+/// Adds `contents` to the end of a data file, creating it if needed.
+/// Does nothing on the web.
+pub fn append_data_file(path: &str, contents: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io::Write;
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()));
+        if let Err(e) = result {
+            println!("Could not write {:?}: {:?}", path, e);
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = (path, contents);
+}
+
+/// Splits a data file into whitespace separated tokens per line.
+/// Blank lines and lines starting with '#' are skipped.
+/// Line numbers start at 1 for error messages.
+pub fn data_lines(source: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    source.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            None
+        } else {
+            Some((i + 1, line.split_whitespace().collect()))
+        }
+    })
+}
+
+pub fn parse_token<T: std::str::FromStr>(
+    tokens: &[&str],
+    index: usize,
+    line: usize,
+) -> Result<T, String> {
+    let token = tokens
+        .get(index)
+        .ok_or_else(|| format!("Line {}: expected at least {} values", line, index + 1))?;
+    token
+        .parse()
+        .map_err(|_| format!("Line {}: could not parse {:?}", line, token))
+}
+
+pub fn parse_vec2(tokens: &[&str], index: usize, line: usize) -> Result<Vec2, String> {
+    Ok(Vec2::new(
+        parse_token(tokens, index, line)?,
+        parse_token(tokens, index + 1, line)?,
+    ))
+}
+
+/// Parses every pair of tokens from `index` to the end of the line.
+pub fn parse_points(tokens: &[&str], index: usize, line: usize) -> Result<Vec<Vec2>, String> {
+    if tokens.len() < index || (tokens.len() - index) % 2 != 0 {
+        return Err(format!("Line {}: expected pairs of x and y values", line));
+    }
+    (index..tokens.len())
+        .step_by(2)
+        .map(|i| parse_vec2(tokens, i, line))
+        .collect()
+}
@@ -0,0 +1,357 @@
+use crate::*;
+use std::sync::Arc;
+
+/// How a `GrowablePlant` grows once it's collected.
+/// Declared in `assets/plants.txt`.
+pub struct PlantSpecies {
+    pub name: String,
+    /// Segments grown from the seed, shared out between branches. Picked between min and max.
+    pub energy: (u32, u32),
+    /// Chance that a stem splits in two at each segment.
+    pub branch_chance: f32,
+    /// Stems stop splitting after this many branches back to the seed.
+    pub max_depth: usize,
+    /// How far the first segment can turn from straight up, as a fraction of a half turn.
+    pub sprout_angle: f32,
+    /// How far each segment can turn from the one before it, as a fraction of a half turn.
+    pub turn_angle: f32,
+    /// Multiplies `plant_segment_length` from `assets/tuning.txt`.
+    pub segment_scale: f32,
+    /// Most gold pegs a stem can grow. Each branch starts with its parent's remaining gold.
+    pub gold_yield: usize,
+    /// Chance that a segment is gold while its stem has gold left.
+    pub gold_chance: f32,
+    /// Seconds between segments.
+    pub delay: f32,
+    /// Turns a segment aside when it would grow into something, instead of stopping the stem.
+    pub steer: bool,
+}
+
+impl PlantSpecies {
+    /// Grows like the original plants.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            energy: (3, 19),
+            branch_chance: 0.2,
+            max_depth: usize::MAX,
+            sprout_angle: 0.5,
+            turn_angle: 0.4,
+            segment_scale: 1.0,
+            gold_yield: 3,
+            gold_chance: 0.2,
+            delay: 0.2,
+            steer: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlantSpeciesId(usize);
+
+pub struct PlantSpeciesRegistry {
+    species: Vec<Arc<PlantSpecies>>,
+}
+
+pub const PLANTS_PATH: &str = "assets/plants.txt";
+const DEFAULT_PLANTS: &str = include_str!("../assets/plants.txt");
+
+impl PlantSpeciesRegistry {
+    /// Loads the species listed in `assets/plants.txt`.
+    /// Always has at least one species so seeds can grow.
+    pub fn load() -> Self {
+        let mut registry = match Self::parse(&read_data_file(PLANTS_PATH, DEFAULT_PLANTS)) {
+            Ok(registry) => registry,
+            Err(e) => {
+                println!("Could not load {:?}: {}", PLANTS_PATH, e);
+                Self {
+                    species: Vec::new(),
+                }
+            }
+        };
+        if registry.species.is_empty() {
+            registry.species.push(Arc::new(PlantSpecies::new("Sprout")));
+        }
+        registry
+    }
+
+    /// See `assets/plants.txt` for the format.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut species: Vec<PlantSpecies> = Vec::new();
+
+        for (line, tokens) in data_lines(source) {
+            if tokens[0] == "species" {
+                species.push(PlantSpecies::new(&parse_token::<String>(&tokens, 1, line)?));
+                continue;
+            }
+
+            let current = species
+                .last_mut()
+                .ok_or_else(|| format!("Line {}: expected 'species <name>' first", line))?;
+
+            match tokens[0] {
+                "energy" => {
+                    current.energy = (
+                        parse_token(&tokens, 1, line)?,
+                        parse_token(&tokens, 2, line)?,
+                    );
+                    if current.energy.0 > current.energy.1 {
+                        return Err(format!("Line {}: energy min is above max", line));
+                    }
+                }
+                "branch_chance" => current.branch_chance = parse_token(&tokens, 1, line)?,
+                "max_depth" => current.max_depth = parse_token(&tokens, 1, line)?,
+                "sprout_angle" => current.sprout_angle = parse_token(&tokens, 1, line)?,
+                "turn_angle" => current.turn_angle = parse_token(&tokens, 1, line)?,
+                "segment_scale" => current.segment_scale = parse_token(&tokens, 1, line)?,
+                "gold_yield" => current.gold_yield = parse_token(&tokens, 1, line)?,
+                "gold_chance" => current.gold_chance = parse_token(&tokens, 1, line)?,
+                "delay" => current.delay = parse_token(&tokens, 1, line)?,
+                "steer" => current.steer = parse_token(&tokens, 1, line)?,
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+
+        Ok(Self {
+            species: species.into_iter().map(Arc::new).collect(),
+        })
+    }
+
+    /// Returns a shared handle so the registry doesn't stay borrowed while the plant grows.
+    pub fn get(&self, id: PlantSpeciesId) -> Arc<PlantSpecies> {
+        self.species[id.0].clone()
+    }
+
+    pub fn find(&self, name: &str) -> Option<PlantSpeciesId> {
+        self.species
+            .iter()
+            .position(|s| s.name == name)
+            .map(PlantSpeciesId)
+    }
+
+    pub fn random(&self, random: &mut Random) -> PlantSpeciesId {
+        PlantSpeciesId(random.range_u32(0..self.species.len() as u32) as usize)
+    }
+}
+
+/// One growing tip of a plant.
+#[derive(Clone, Copy)]
+struct GrowingTip {
+    species: PlantSpeciesId,
+    position: Vec2,
+    direction: Vec2,
+    energy: u32,
+    gold: usize,
+    /// Branches back to the seed.
+    depth: usize,
+}
+
+/// Turns `direction` by a random angle up to `angle` half turns either way.
+fn random_turn(resources: &Resources, direction: Vec2, angle: f32) -> Vec2 {
+    let range = std::f32::consts::PI * angle;
+    let rotation = range * -1.0 + resources.get::<GameRandom>().f32() * range * 2.0;
+
+    let rotation = Quat::from_angle_axis(rotation, Vec3::Z);
+    rotation.rotate_vector3(direction.extend(0.0)).xy()
+}
+
+/// Grows a plant from a collected `GrowablePlant` at `position`.
+/// Seeds without a species pick one at random.
+/// `half_board` is half the size of the board, which is centered on the origin.
+/// Plants don't grow past it.
+pub fn grow_plant(
+    world: &mut World,
+    resources: &mut Resources,
+    position: Vec2,
+    species: Option<PlantSpeciesId>,
+    half_board: Vec2,
+) {
+    let species = species.unwrap_or_else(|| {
+        resources
+            .get::<PlantSpeciesRegistry>()
+            .random(&mut resources.get::<GameRandom>())
+    });
+    let definition = resources.get::<PlantSpeciesRegistry>().get(species);
+
+    let direction = random_turn(resources, Vec2::Y, definition.sprout_angle);
+    let (min, max) = definition.energy;
+    let energy = resources.get::<GameRandom>().range_u32(min..max + 1);
+
+    // The seed itself is the first segment.
+    grow_branches(
+        world,
+        resources,
+        GrowingTip {
+            species,
+            position,
+            direction,
+            energy,
+            gold: definition.gold_yield,
+            depth: 0,
+        },
+        half_board,
+    );
+}
+
+/// Spawns the peg for `stem`, then grows the next segments after the species' delay.
+fn grow_segment(
+    world: &mut World,
+    resources: &mut Resources,
+    mut stem: GrowingTip,
+    half_board: Vec2,
+) {
+    let species = resources.get::<PlantSpeciesRegistry>().get(stem.species);
+    let stem_vector = stem.direction * segment_length(resources, &species);
+
+    if stem.energy == 1 && stem.gold == 1 {
+        spawn_gold(world, resources, stem.position, stem_vector);
+    } else {
+        let gold_here = resources.get::<GameRandom>().f32() < species.gold_chance;
+        if stem.gold > 0 && gold_here {
+            spawn_gold(world, resources, stem.position, stem_vector);
+            stem.gold -= 1;
+        } else {
+            spawn_plant(world, resources, stem.position, stem_vector);
+        }
+    }
+    grow_branches(world, resources, stem, half_board);
+}
+
+/// Uses up one energy at `stem`, then splits what's left between one or two new segments.
+fn grow_branches(world: &mut World, resources: &mut Resources, stem: GrowingTip, half_board: Vec2) {
+    let energy = stem.energy.saturating_sub(1);
+    if energy == 0 {
+        return;
+    }
+    let species = resources.get::<PlantSpeciesRegistry>().get(stem.species);
+
+    world.spawn((DelayedAction::new(
+        move |world, resources| {
+            let mut branches = vec![GrowingTip { energy, ..stem }];
+
+            let branch = resources.get::<GameRandom>().f32() < species.branch_chance;
+            if branch && energy > 1 && stem.depth < species.max_depth {
+                let transfer = resources.get::<GameRandom>().range_u32(1..energy);
+                branches[0].energy -= transfer;
+                branches.push(GrowingTip {
+                    energy: transfer,
+                    depth: stem.depth + 1,
+                    ..stem
+                });
+            }
+
+            for mut branch in branches {
+                let direction = random_turn(resources, stem.direction, species.turn_angle);
+                let length = segment_length(resources, &species);
+                let free = find_space(
+                    world,
+                    half_board,
+                    stem.position,
+                    direction,
+                    length,
+                    species.steer,
+                );
+                branch.direction = match free {
+                    Some(direction) => direction,
+                    None => continue,
+                };
+                branch.position = stem.position + branch.direction * length;
+                grow_segment(world, resources, branch, half_board);
+            }
+        },
+        species.delay,
+    ),));
+}
+
+fn segment_length(resources: &Resources, species: &PlantSpecies) -> f32 {
+    resources.get::<Tuning>().plant_segment_length * species.segment_scale
+}
+
+/// Roughly the radius of a peg's collider.
+const PEG_CLEARANCE: f32 = 3.0;
+/// Pegs closer together than this overlap.
+const PEG_SPACING: f32 = 5.5;
+/// Keeps plants from growing over the witch, where shots start.
+const WITCH_CLEARANCE: f32 = 20.0;
+/// Turns tried in order when a segment is blocked, as fractions of a half turn.
+const STEER_TURNS: [f32; 6] = [0.15, -0.15, 0.3, -0.3, 0.45, -0.45];
+
+/// The first direction from `position`, starting with `direction`, with room for a segment.
+/// Only `direction` itself is tried without `steer`.
+fn find_space(
+    world: &World,
+    half_board: Vec2,
+    position: Vec2,
+    direction: Vec2,
+    length: f32,
+    steer: bool,
+) -> Option<Vec2> {
+    let turns: &[f32] = if steer { &STEER_TURNS } else { &[] };
+    std::iter::once(0.0)
+        .chain(turns.iter().copied())
+        .map(|turn| {
+            let rotation = Quat::from_angle_axis(std::f32::consts::PI * turn, Vec3::Z);
+            rotation.rotate_vector3(direction.extend(0.0)).xy()
+        })
+        .find(|direction| !is_blocked(world, half_board, position + *direction * length))
+}
+
+/// True if a peg at `position` would be off the board, over the witch,
+/// or overlapping another peg or brick.
+/// Bricks are only found in worlds with a `RapierIntegration`.
+fn is_blocked(world: &World, half_board: Vec2, position: Vec2) -> bool {
+    let half_board = half_board - Vec2::fill(PEG_CLEARANCE);
+    if position.x.abs() > half_board.x || position.y.abs() > half_board.y {
+        return true;
+    }
+    if (position - shot_origin().xy()).length() < WITCH_CLEARANCE {
+        return true;
+    }
+
+    // Pegs grown this frame aren't in the physics queries until the next step,
+    // so pegs are checked directly. Pegs that were hit are about to be cleared.
+    let near_peg = world
+        .query::<(&Peg, &Transform)>()
+        .iter()
+        .any(|(_, (peg, transform))| {
+            !peg.hit && (transform.position.xy() - position).length() < PEG_SPACING
+        });
+    if near_peg {
+        return true;
+    }
+
+    let mut q = world.query::<&RapierIntegration>();
+    let rapier_integration = match q.iter().next() {
+        Some((_, rapier_integration)) => rapier_integration,
+        None => return false,
+    };
+    rapier_integration
+        .entities_in_circle(position, PEG_CLEARANCE)
+        .into_iter()
+        .any(|e| world.get::<&Peg>(e).is_err())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_species_parse() {
+        let registry = PlantSpeciesRegistry::parse(DEFAULT_PLANTS).unwrap();
+        for name in ["Sprout", "Vine", "Bush", "Goldenrod"] {
+            assert!(registry.find(name).is_some(), "{} is missing", name);
+        }
+        let goldenrod = registry.get(registry.find("Goldenrod").unwrap());
+        assert_eq!(goldenrod.energy, (4, 8));
+        assert!(!goldenrod.steer);
+        // Left out, so it keeps the original sprout's value.
+        assert_eq!(goldenrod.segment_scale, 1.0);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(PlantSpeciesRegistry::parse("energy 1 2").is_err());
+        assert!(PlantSpeciesRegistry::parse("species A\nenergy 5 2").is_err());
+        assert!(PlantSpeciesRegistry::parse("species A\nleaves 3").is_err());
+    }
+}
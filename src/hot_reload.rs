@@ -0,0 +1,82 @@
+use crate::*;
+
+/// Checks data files for changes so they can be reloaded while the game runs.
+/// Only used on native builds.
+pub struct HotReload {
+    files: Vec<(String, Option<std::time::SystemTime>)>,
+    frames_until_check: usize,
+    /// The level changed while a shot was in flight or the shop was open.
+    level_reload_pending: bool,
+}
+
+/// Checking the disk every frame is wasteful, so only check about once a second.
+const FRAMES_BETWEEN_CHECKS: usize = 60;
+
+impl HotReload {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            frames_until_check: FRAMES_BETWEEN_CHECKS,
+            level_reload_pending: false,
+        }
+    }
+
+    /// True if `path` was modified since it was last checked.
+    /// The first check of a path only records its time.
+    fn changed(&mut self, path: &str) -> bool {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        match self.files.iter_mut().find(|(p, _)| p == path) {
+            Some((_, last_modified)) => {
+                let changed = modified.is_some() && *last_modified != modified;
+                *last_modified = modified;
+                changed
+            }
+            None => {
+                self.files.push((path.to_string(), modified));
+                false
+            }
+        }
+    }
+
+    /// Reloads the tuning, asset manifest and current level if they've changed on disk.
+    pub fn run(&mut self, world: &mut World, resources: &mut Resources, audio: &mut Audio) {
+        if self.frames_until_check > 0 {
+            self.frames_until_check -= 1;
+            return;
+        }
+        self.frames_until_check = FRAMES_BETWEEN_CHECKS;
+
+        if self.changed(TUNING_PATH) {
+            println!("Reloading {:?}", TUNING_PATH);
+            let tuning = Tuning::load();
+            let restitution = tuning.restitution;
+            resources.add(tuning);
+
+            let mut level_state = resources.get::<LevelState>();
+            for world in [&mut *world, &mut level_state.other_world] {
+                for (_, rapier_integration) in world.query_mut::<&mut RapierIntegration>() {
+                    rapier_integration.set_restitution(restitution);
+                }
+            }
+        }
+
+        if self.changed(MANIFEST_PATH) {
+            println!("Reloading {:?}", MANIFEST_PATH);
+            resources.add(AssetManifest::load());
+            resources.get::<GameAssets>().apply_manifest(resources);
+            audio.load_peg_sounds(resources);
+        }
+
+        let level_path = resources.get::<LevelState>().level_path.clone();
+        if self.changed(&level_path) {
+            println!(
+                "Reloading {:?} before the next shot on the board",
+                level_path
+            );
+            self.level_reload_pending = true;
+        }
+        if self.level_reload_pending && reload_board(world, resources) {
+            self.level_reload_pending = false;
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use crate::*;
+
+/// A board described by a level file in `assets/levels`.
+/// See `assets/levels/farm.level` for the format.
+pub struct Level {
+    /// Where the level was loaded from, so it can be loaded again.
+    pub path: String,
+    pub starting_gold: i32,
+    pub win_gold: i32,
+    /// How many bounces the aim preview shows. Fewer makes the level harder.
+    pub aim_preview_bounces: usize,
+    /// Balls the player gets before the round ends. 0 for no limit.
+    pub balls_per_round: usize,
+    /// The level to play after this one's round ends. Repeats this level if `None`.
+    pub next_level: Option<String>,
+    pub elements: Vec<LevelElement>,
+}
+
+pub enum LevelElement {
+    Peg {
+        position: Vec2,
+        peg_type: PegType,
+    },
+    /// A `GrowablePlant` that grows as a particular species from `assets/plants.txt`.
+    Seed {
+        position: Vec2,
+        species: String,
+    },
+    /// Pegs scattered randomly within a rectangle.
+    RandomPegs {
+        peg_type: PegType,
+        count: usize,
+        min: Vec2,
+        max: Vec2,
+    },
+    RockStorm {
+        peg_type: PegType,
+        turn_rate: f32,
+        radius_rate: f32,
+        count: usize,
+        center: Vec2,
+        start_radius: f32,
+    },
+    Brick {
+        position: Vec2,
+        dimensions: Vec2,
+    },
+    /// A peg that follows a path, starting wherever the path puts it.
+    MovingPeg {
+        peg_type: PegType,
+        mover: PathMover,
+    },
+    MovingBrick {
+        dimensions: Vec2,
+        mover: PathMover,
+    },
+    /// A peg hanging from `pivot` that starts at `position` and swings freely.
+    Pendulum {
+        peg_type: PegType,
+        pivot: Vec2,
+        position: Vec2,
+    },
+    /// A thin wall through each point in turn, for curved rails.
+    Rail {
+        points: Vec<Vec2>,
+    },
+    /// An angled bumper shaped like the convex hull of `points`, which are offsets from `position`.
+    Bumper {
+        position: Vec2,
+        points: Vec<Vec2>,
+    },
+    /// Bricks joined into one body, for shapes like an L.
+    /// Each part is an offset from `position` and the part's dimensions.
+    CompoundBrick {
+        position: Vec2,
+        parts: Vec<(Vec2, Vec2)>,
+    },
+    /// A bucket sliding along the bottom of the board that refunds balls it catches.
+    Catcher {
+        width: f32,
+        period: f32,
+    },
+    /// A brick in the shop holding a randomly selected powerup.
+    ShopBrick {
+        position: Vec2,
+    },
+}
+
+pub const DEFAULT_LEVEL_PATH: &str = "assets/levels/farm.level";
+const DEFAULT_LEVEL: &str = include_str!("../assets/levels/farm.level");
+const ORCHARD_LEVEL_PATH: &str = "assets/levels/orchard.level";
+const ORCHARD_LEVEL: &str = include_str!("../assets/levels/orchard.level");
+
+impl Level {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let embedded = match path {
+            DEFAULT_LEVEL_PATH => DEFAULT_LEVEL,
+            ORCHARD_LEVEL_PATH => ORCHARD_LEVEL,
+            _ if data_file_exists(path) => "",
+            _ => return Err(format!("{}: no such level", path)),
+        };
+        let mut level =
+            Self::parse(&read_data_file(path, embedded)).map_err(|e| format!("{}: {}", path, e))?;
+        level.path = path.to_string();
+        Ok(level)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut level = Level {
+            path: String::new(),
+            starting_gold: 20,
+            win_gold: 1000,
+            aim_preview_bounces: 1,
+            balls_per_round: 0,
+            next_level: None,
+            elements: Vec::new(),
+        };
+
+        for (line, tokens) in data_lines(source) {
+            let peg_type = |index: usize| {
+                let name = tokens.get(index).copied().unwrap_or("");
+                PegType::from_name(name)
+                    .ok_or_else(|| format!("Line {}: unknown peg type {:?}", line, name))
+            };
+
+            match tokens[0] {
+                "starting_gold" => level.starting_gold = parse_token(&tokens, 1, line)?,
+                "win_gold" => level.win_gold = parse_token(&tokens, 1, line)?,
+                "balls_per_round" => level.balls_per_round = parse_token(&tokens, 1, line)?,
+                "next_level" => level.next_level = Some(parse_token(&tokens, 1, line)?),
+                "aim_preview_bounces" => level.aim_preview_bounces = parse_token(&tokens, 1, line)?,
+                "peg" => level.elements.push(LevelElement::Peg {
+                    peg_type: peg_type(1)?,
+                    position: parse_vec2(&tokens, 2, line)?,
+                }),
+                "seed" => level.elements.push(LevelElement::Seed {
+                    species: parse_token(&tokens, 1, line)?,
+                    position: parse_vec2(&tokens, 2, line)?,
+                }),
+                "random_pegs" => {
+                    let min = parse_vec2(&tokens, 3, line)?;
+                    let max = parse_vec2(&tokens, 5, line)?;
+                    if min.x > max.x || min.y > max.y {
+                        return Err(format!("Line {}: random_pegs min is above max", line));
+                    }
+                    level.elements.push(LevelElement::RandomPegs {
+                        peg_type: peg_type(1)?,
+                        count: parse_token(&tokens, 2, line)?,
+                        min,
+                        max,
+                    })
+                }
+                "rock_storm" => level.elements.push(LevelElement::RockStorm {
+                    peg_type: peg_type(1)?,
+                    turn_rate: parse_token(&tokens, 2, line)?,
+                    radius_rate: parse_token(&tokens, 3, line)?,
+                    count: parse_token(&tokens, 4, line)?,
+                    center: parse_vec2(&tokens, 5, line)?,
+                    start_radius: parse_token(&tokens, 7, line)?,
+                }),
+                "brick" => level.elements.push(LevelElement::Brick {
+                    position: parse_vec2(&tokens, 1, line)?,
+                    dimensions: parse_vec2(&tokens, 3, line)?,
+                }),
+                "moving_peg" => level.elements.push(LevelElement::MovingPeg {
+                    peg_type: peg_type(1)?,
+                    mover: PathMover::parse(&tokens, 2, line)?,
+                }),
+                "moving_brick" => level.elements.push(LevelElement::MovingBrick {
+                    dimensions: parse_vec2(&tokens, 1, line)?,
+                    mover: PathMover::parse(&tokens, 3, line)?,
+                }),
+                "pendulum" => level.elements.push(LevelElement::Pendulum {
+                    peg_type: peg_type(1)?,
+                    pivot: parse_vec2(&tokens, 2, line)?,
+                    position: parse_vec2(&tokens, 4, line)?,
+                }),
+                "rail" => {
+                    let points = parse_points(&tokens, 1, line)?;
+                    if points.len() < 2 {
+                        return Err(format!("Line {}: a rail needs at least two points", line));
+                    }
+                    level.elements.push(LevelElement::Rail { points })
+                }
+                "bumper" => level.elements.push(LevelElement::Bumper {
+                    position: parse_vec2(&tokens, 1, line)?,
+                    points: parse_bumper_points(&tokens, 3, line)?,
+                }),
+                "compound_brick" => level.elements.push(LevelElement::CompoundBrick {
+                    position: parse_vec2(&tokens, 1, line)?,
+                    parts: parse_compound_brick_parts(&tokens, 3, line)?,
+                }),
+                "catcher" => level.elements.push(LevelElement::Catcher {
+                    width: parse_token(&tokens, 1, line)?,
+                    period: parse_token(&tokens, 2, line)?,
+                }),
+                "shop_brick" => level.elements.push(LevelElement::ShopBrick {
+                    position: parse_vec2(&tokens, 1, line)?,
+                }),
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+        Ok(level)
+    }
+
+    /// Spawns the board into `world` and the powerup bricks into `shop_world`.
+    pub fn spawn(&self, world: &mut World, shop_world: &mut World, resources: &mut Resources) {
+        {
+            let mut ui_state = resources.get::<UIState>();
+            ui_state.gold = self.starting_gold;
+            ui_state.win_gold = self.win_gold;
+        }
+        self.spawn_elements(world, shop_world, resources);
+    }
+
+    /// Like `spawn`, but leaves gold alone for moving on to the next board.
+    pub fn spawn_elements(
+        &self,
+        world: &mut World,
+        shop_world: &mut World,
+        resources: &mut Resources,
+    ) {
+        for element in &self.elements {
+            match *element {
+                LevelElement::Peg { position, peg_type } => {
+                    spawn_peg(world, resources, position, peg_type);
+                }
+                LevelElement::Seed {
+                    position,
+                    ref species,
+                } => {
+                    let seed = spawn_peg(world, resources, position, PegType::GrowablePlant);
+                    let species_id = resources.get::<PlantSpeciesRegistry>().find(species);
+                    if species_id.is_none() {
+                        println!("No plant species named {:?} in {:?}", species, PLANTS_PATH);
+                    }
+                    if let Ok(mut plant) = world.get::<&mut Plant>(seed) {
+                        plant.species = species_id;
+                    }
+                }
+                LevelElement::RandomPegs {
+                    peg_type,
+                    count,
+                    min,
+                    max,
+                } => {
+                    for _ in 0..count {
+                        let position = {
+                            let mut random = resources.get::<GameRandom>();
+                            Vec2::new(
+                                pick_f32(&mut random, (min.x, max.x)),
+                                pick_f32(&mut random, (min.y, max.y)),
+                            )
+                        };
+                        spawn_peg(world, resources, position, peg_type);
+                    }
+                }
+                LevelElement::RockStorm {
+                    peg_type,
+                    turn_rate,
+                    radius_rate,
+                    count,
+                    center,
+                    start_radius,
+                } => apply_rock_storm(
+                    world,
+                    resources,
+                    peg_type,
+                    turn_rate,
+                    radius_rate,
+                    count,
+                    center,
+                    start_radius,
+                ),
+                LevelElement::Brick {
+                    position,
+                    dimensions,
+                } => {
+                    spawn_brick(world, resources, position, dimensions);
+                }
+                LevelElement::MovingPeg {
+                    peg_type,
+                    ref mover,
+                } => {
+                    let position = mover.path.position(mover.t);
+                    let peg = spawn_peg(world, resources, position, peg_type);
+                    let _ = world.insert_one(peg, mover.clone());
+                }
+                LevelElement::MovingBrick {
+                    dimensions,
+                    ref mover,
+                } => {
+                    let position = mover.path.position(mover.t);
+                    let brick = spawn_brick(world, resources, position, dimensions);
+                    let _ = world.insert_one(brick, mover.clone());
+                }
+                LevelElement::Pendulum {
+                    peg_type,
+                    pivot,
+                    position,
+                } => {
+                    spawn_pendulum(world, resources, peg_type, pivot, position);
+                }
+                LevelElement::Rail { ref points } => {
+                    for pair in points.windows(2) {
+                        spawn_rail(world, resources, pair[0], pair[1]);
+                    }
+                }
+                LevelElement::Bumper {
+                    position,
+                    ref points,
+                } => {
+                    spawn_bumper(world, resources, position, points.clone());
+                }
+                LevelElement::CompoundBrick {
+                    position,
+                    ref parts,
+                } => {
+                    spawn_compound_brick(world, resources, position, parts.clone());
+                }
+                LevelElement::Catcher { width, period } => {
+                    spawn_catcher(world, resources, width, period);
+                }
+                LevelElement::ShopBrick { position } => {
+                    if let Some(p) = select_powerup(shop_world, resources) {
+                        spawn_brick_with_powerup(shop_world, resources, position, p);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses the `<dx> <dy>` corners of a bumper, from `index` on.
+/// A bumper needs three corners that aren't all on a line to have a convex hull.
+pub fn parse_bumper_points(
+    tokens: &[&str],
+    index: usize,
+    line: usize,
+) -> Result<Vec<Vec2>, String> {
+    let points = parse_points(tokens, index, line)?;
+    let on_a_line = |points: &[Vec2]| {
+        let u = points[1] - points[0];
+        points.iter().all(|p| {
+            let v = *p - points[0];
+            (u.x * v.y - u.y * v.x).abs() < 0.001
+        })
+    };
+    if points.len() < 3 || on_a_line(&points) {
+        return Err(format!(
+            "Line {}: a bumper needs three corners that aren't on a line",
+            line
+        ));
+    }
+    Ok(points)
+}
+
+/// Parses `<dx> <dy> <width> <height>` for each part of a compound brick, from `index` on.
+pub fn parse_compound_brick_parts(
+    tokens: &[&str],
+    index: usize,
+    line: usize,
+) -> Result<Vec<(Vec2, Vec2)>, String> {
+    let points = parse_points(tokens, index, line)?;
+    if points.is_empty() || points.len() % 2 != 0 {
+        return Err(format!(
+            "Line {}: expected an offset and dimensions for each part",
+            line
+        ));
+    }
+    Ok(points.chunks(2).map(|part| (part[0], part[1])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_levels_parse() {
+        for path in [DEFAULT_LEVEL_PATH, ORCHARD_LEVEL_PATH] {
+            let level = Level::load(path).unwrap();
+            assert_eq!(level.path, path);
+            assert!(!level.elements.is_empty());
+        }
+    }
+
+    #[test]
+    fn header_values() {
+        let level = Level::parse(
+            "starting_gold 7\nwin_gold 50\nballs_per_round 3\nnext_level a.level\naim_preview_bounces 2",
+        )
+        .unwrap();
+        assert_eq!(level.starting_gold, 7);
+        assert_eq!(level.win_gold, 50);
+        assert_eq!(level.balls_per_round, 3);
+        assert_eq!(level.next_level.as_deref(), Some("a.level"));
+        assert_eq!(level.aim_preview_bounces, 2);
+    }
+
+    #[test]
+    fn elements() {
+        let level = Level::parse(
+            "peg Gold 1 2\n\
+             rail 0 0 10 0 10 10\n\
+             compound_brick 5 5 0 0 20 4 -8 -6 4 12\n\
+             pendulum Stone 0 40 10 30\n\
+             moving_peg Plant 4 0.5 orbit 0 0 10\n\
+             bumper -20 0 0 0 10 5 0 10",
+        )
+        .unwrap();
+        assert_eq!(level.elements.len(), 6);
+        match &level.elements[0] {
+            LevelElement::Peg { position, peg_type } => {
+                assert_eq!(*position, Vec2::new(1.0, 2.0));
+                assert!(*peg_type == PegType::Gold);
+            }
+            _ => panic!("expected a peg"),
+        }
+        match &level.elements[1] {
+            LevelElement::Rail { points } => assert_eq!(points.len(), 3),
+            _ => panic!("expected a rail"),
+        }
+        match &level.elements[2] {
+            LevelElement::CompoundBrick { parts, .. } => {
+                assert_eq!(parts.len(), 2);
+                assert_eq!(parts[1], (Vec2::new(-8.0, -6.0), Vec2::new(4.0, 12.0)));
+            }
+            _ => panic!("expected a compound brick"),
+        }
+        match &level.elements[3] {
+            LevelElement::Pendulum {
+                pivot, position, ..
+            } => {
+                assert_eq!(*pivot, Vec2::new(0.0, 40.0));
+                assert_eq!(*position, Vec2::new(10.0, 30.0));
+            }
+            _ => panic!("expected a pendulum"),
+        }
+        match &level.elements[4] {
+            LevelElement::MovingPeg { mover, .. } => {
+                assert_eq!(mover.period, 4.0);
+                assert_eq!(mover.t, 0.5);
+            }
+            _ => panic!("expected a moving peg"),
+        }
+        match &level.elements[5] {
+            LevelElement::Bumper { position, points } => {
+                assert_eq!(*position, Vec2::new(-20.0, 0.0));
+                assert_eq!(points.len(), 3);
+            }
+            _ => panic!("expected a bumper"),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Level::parse("peg Diamond 0 0").is_err());
+        assert!(Level::parse("peg Gold 0").is_err());
+        assert!(Level::parse("volcano 0 0").is_err());
+        assert!(Level::parse("rail 0 0").is_err());
+        assert!(Level::parse("compound_brick 0 0 1 1 2").is_err());
+        assert!(Level::parse("bumper 0 0 0 0 10 0").is_err());
+        assert!(Level::parse("bumper 0 0 0 0 10 0 20 0").is_err());
+        assert!(Level::parse("random_pegs Gold 3 10 0 -10 5").is_err());
+        assert_eq!(
+            Level::load("missing.level").err().unwrap(),
+            "missing.level: no such level"
+        );
+    }
+
+    #[test]
+    fn random_pegs_with_equal_bounds() {
+        let level = Level::parse("random_pegs Gold 3 5 0 5 10").unwrap();
+        assert_eq!(level.elements.len(), 1);
+    }
+}
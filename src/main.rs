@@ -9,92 +9,119 @@ use koi_graphics_context::BlendFactor;
 use temporary::*;
 mod ui;
 use ui::*;
+mod data_file;
+use data_file::*;
+mod level;
+use level::*;
+mod simulation;
+use simulation::*;
+mod replay;
+use replay::*;
+mod save;
+use save::*;
+mod powerups;
+use powerups::*;
+mod path;
+use path::*;
+mod round;
+use round::*;
+mod setup;
+use setup::*;
+mod settings;
+use settings::*;
+mod menu;
+use menu::*;
+mod palette;
+use palette::*;
+mod audio;
+use audio::*;
+mod manifest;
+use manifest::*;
+mod tuning;
+use tuning::*;
+mod hot_reload;
+use hot_reload::*;
+mod growth;
+use growth::*;
 
 struct LevelState {
-    pitch_multiplier: f32,
+    /// Pegs hit so far this shot, for the pitch ladder.
+    combo: usize,
     aiming: bool,
     ready_to_shoot: bool,
     collected_pegs: Vec<Entity>,
+    peg_hits: Vec<PegHit>,
     other_world: World,
     in_shop: bool,
     screen_shake_amount: f32,
-    effects_to_apply_to_next_ball: Vec<Effects>,
+    effects_to_apply_to_next_ball: Vec<PowerupId>,
     victory: bool,
     fired_once: bool,
     multiplier: f32,
-    screen_shake_enabled: bool,
+    /// How many bounces the aim preview shows, before powerups.
+    aim_preview_bounces: usize,
+    /// Only one ball per shot is refunded by the catcher.
+    shot_refunded: bool,
+    /// `None` if the level doesn't limit balls.
+    balls_remaining: Option<usize>,
+    /// Set after the last ball of a round until the player moves on to the next board.
+    round_over: bool,
+    round_stats: RoundStats,
+    run_stats: RoundStats,
+    /// Rounds that ran out of balls, whether or not the board was cleared.
+    boards_played: usize,
+    game_over: bool,
+    level_path: String,
+    next_level: Option<String>,
 }
 
-#[derive(Clone, PartialEq, Debug)]
-enum Effects {
-    BigBall,
-    RockStorm,
-    RocksToGold,
-    SeedStorm,
-    MultiBallStorm,
-    MultiplierStorm,
-    RockWall,
-    RocksToMultiball,
+struct MouseFocalPoint;
+
+/// The one source of randomness for gameplay, so a run can be reproduced from its seed.
+/// Purely visual randomness (like screen shake) doesn't need to use this.
+struct GameRandom {
+    seed: u64,
+    random: Random,
 }
 
-const BIG_BALL: Powerup = Powerup {
-    cost: 3,
-    description: "Bigger Ball",
-    effect: Effects::BigBall,
-};
-const ROCK_STORM: Powerup = Powerup {
-    cost: 10,
-    description: "Rock Storm",
-    effect: Effects::RockStorm,
-};
-
-const SEED_FRENZY: Powerup = Powerup {
-    cost: 20,
-    description: "Plant a Seed",
-    effect: Effects::SeedStorm,
-};
-const ROCKS_TO_GOLD: Powerup = Powerup {
-    cost: 700,
-    description: "Rocks To Gold",
-    effect: Effects::RocksToGold,
-};
-
-const ROCKS_TO_MULTIBALL: Powerup = Powerup {
-    cost: 15,
-    description: "Rocks To Multiball",
-    effect: Effects::RocksToMultiball,
-};
-
-const MULTIBALL_STORM: Powerup = Powerup {
-    cost: 5,
-    description: "Multiballs!",
-    effect: Effects::MultiBallStorm,
-};
-
-const MULTIPLIER_STORM: Powerup = Powerup {
-    cost: 15,
-    description: "Multipliers!",
-    effect: Effects::MultiplierStorm,
-};
-
-const ROCK_WALL: Powerup = Powerup {
-    cost: 20,
-    description: "Rock Walls!",
-    effect: Effects::RockWall,
-};
-
-const POWERUPS: [Powerup; 8] = [
-    BIG_BALL,
-    ROCK_STORM,
-    ROCKS_TO_GOLD,
-    SEED_FRENZY,
-    MULTIBALL_STORM,
-    MULTIPLIER_STORM,
-    ROCK_WALL,
-    ROCKS_TO_MULTIBALL,
-];
+impl GameRandom {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            random: Random::new_with_seed(seed),
+        }
+    }
+}
 
-struct MouseFocalPoint;
+impl std::ops::Deref for GameRandom {
+    type Target = Random;
+    fn deref(&self) -> &Random {
+        &self.random
+    }
+}
+
+impl std::ops::DerefMut for GameRandom {
+    fn deref_mut(&mut self) -> &mut Random {
+        &mut self.random
+    }
+}
+
+/// Uses `--seed <seed>` if passed, otherwise picks a new seed.
+fn seed_from_command_line() -> u64 {
+    let mut args = std::env::args().skip_while(|a| a != "--seed");
+    args.next();
+    args.next()
+        .and_then(|a| a.parse().ok())
+        .unwrap_or_else(|| Random::new().range_u32(0..u32::MAX) as u64)
+}
+
+/// Uses `--level <path>` if passed, otherwise the first level.
+fn level_from_command_line() -> String {
+    let mut args = std::env::args().skip_while(|a| a != "--level");
+    args.next();
+    args.next()
+        .unwrap_or_else(|| DEFAULT_LEVEL_PATH.to_string())
+}
 
 fn apply_rock_storm(
     world: &mut World,
@@ -106,8 +133,7 @@ fn apply_rock_storm(
     center: Vec2,
     start_radius: f32,
 ) {
-    let mut random = Random::new();
-    let mut angle = random.f32() * std::f32::consts::TAU;
+    let mut angle = resources.get::<GameRandom>().f32() * std::f32::consts::TAU;
     let mut radius = start_radius;
 
     let mut time_offset = 0.1;
@@ -141,6 +167,7 @@ fn apply_rocks_to_gold(world: &mut World, resources: &mut Resources, from: PegTy
     let mut time_offset = 0.1;
     for rock in rocks {
         let position = world.get::<&Transform>(rock).unwrap().position;
+        let mover = world.get::<&PathMover>(rock).ok().map(|m| (*m).clone());
         world.spawn((DelayedAction::new(
             move |world, resources| {
                 let _ = world.despawn(rock);
@@ -150,7 +177,10 @@ fn apply_rocks_to_gold(world: &mut World, resources: &mut Resources, from: PegTy
         let to = to.clone();
         world.spawn((DelayedAction::new(
             move |world, resources| {
-                spawn_peg(world, resources, position.xy(), to);
+                let peg = spawn_peg(world, resources, position.xy(), to);
+                if let Some(mover) = mover.clone() {
+                    let _ = world.insert_one(peg, mover);
+                }
             },
             time_offset + 0.02,
         ),));
@@ -187,14 +217,14 @@ fn create_rock_wall(world: &mut World) {
     }
 }
 
-const PLANT_SEGMENT_LENGTH: f32 = 8.0;
 impl LevelState {
     pub fn new(other_world: World) -> Self {
         Self {
-            pitch_multiplier: 1.0,
+            combo: 0,
             aiming: true,
             ready_to_shoot: true,
             collected_pegs: Vec::new(),
+            peg_hits: Vec::new(),
             other_world,
             in_shop: false,
             screen_shake_amount: 0.0,
@@ -202,98 +232,48 @@ impl LevelState {
             victory: false,
             fired_once: false,
             multiplier: 1.0,
-            screen_shake_enabled: true,
+            aim_preview_bounces: 1,
+            shot_refunded: false,
+            balls_remaining: None,
+            round_over: false,
+            round_stats: RoundStats::new(),
+            run_stats: RoundStats::new(),
+            boards_played: 0,
+            game_over: false,
+            level_path: DEFAULT_LEVEL_PATH.to_string(),
+            next_level: None,
         }
     }
 
+    /// Starts a round of `level`, which has already been spawned.
+    pub fn start_level(&mut self, level: &Level) {
+        self.aim_preview_bounces = level.aim_preview_bounces;
+        self.balls_remaining = if level.balls_per_round > 0 {
+            Some(level.balls_per_round)
+        } else {
+            None
+        };
+        self.round_over = false;
+        self.round_stats = RoundStats::new();
+        self.level_path = level.path.clone();
+        self.next_level = level.next_level.clone();
+    }
+
     pub fn toggle_shop(&mut self, world: &mut World, resources: &mut Resources) {
         self.in_shop = !self.in_shop;
         std::mem::swap(world, &mut self.other_world);
 
         if !self.in_shop {
-            for effect in self
+            let registry = resources.get::<PowerupRegistry>();
+            let to_apply: Vec<_> = self
                 .effects_to_apply_to_next_ball
-                .drain_filter(|f| match f {
-                    Effects::RockStorm
-                    | Effects::RocksToGold
-                    | Effects::SeedStorm
-                    | Effects::MultiBallStorm
-                    | Effects::MultiplierStorm
-                    | Effects::RocksToMultiball
-                    | Effects::RockWall => true,
-                    _ => false,
-                })
-            {
-                match effect {
-                    Effects::BigBall => {}
-                    Effects::RockWall => create_rock_wall(world),
-                    Effects::RockStorm => {
-                        let mut random = Random::new();
-                        let center =
-                            Vec2::new(random.range_f32(-30.0..30.0), random.range_f32(-40.0..30.));
-                        apply_rock_storm(
-                            world,
-                            resources,
-                            PegType::Stone,
-                            0.05,
-                            1.5,
-                            20,
-                            center,
-                            10.0,
-                        )
-                    }
-                    Effects::MultiBallStorm => {
-                        let mut random = Random::new();
-                        let center =
-                            Vec2::new(random.range_f32(-30.0..30.0), random.range_f32(-40.0..30.));
-                        apply_rock_storm(
-                            world,
-                            resources,
-                            PegType::MultiBall,
-                            Random::new().range_f32(0.02..0.1),
-                            Random::new().range_f32(1.0..4.0),
-                            Random::new().range_u32(3..10) as _,
-                            center,
-                            10.0,
-                        )
-                    }
-                    Effects::RocksToGold => {
-                        apply_rocks_to_gold(world, resources, PegType::Stone, PegType::Gold)
-                    }
-                    Effects::RocksToMultiball => {
-                        apply_rocks_to_gold(world, resources, PegType::Stone, PegType::MultiBall)
-                    }
-                    Effects::SeedStorm => {
-                        let mut random = Random::new();
-                        let center =
-                            Vec2::new(random.range_f32(-30.0..30.0), random.range_f32(-40.0..0.));
-                        apply_rock_storm(
-                            world,
-                            resources,
-                            PegType::GrowablePlant,
-                            0.2,
-                            3.0,
-                            2,
-                            center,
-                            10.0,
-                        )
-                    }
-                    Effects::MultiplierStorm => {
-                        let mut random = Random::new();
-                        let center =
-                            Vec2::new(random.range_f32(-30.0..30.0), random.range_f32(-40.0..40.0));
-                        apply_rock_storm(
-                            world,
-                            resources,
-                            PegType::Multiplier,
-                            0.2,
-                            3.0,
-                            2,
-                            center,
-                            10.0,
-                        )
-                    }
-                }
+                .drain_filter(|id| registry.get(*id).timing() == PowerupTiming::OnShopExit)
+                .map(|id| registry.get(id))
+                .collect();
+            drop(registry);
+
+            for powerup in to_apply {
+                powerup.apply_to_board(world, resources);
             }
         }
     }
@@ -302,7 +282,7 @@ impl LevelState {
         let mut new_gold = 0;
         if !self.in_shop {
             self.ready_to_shoot = true;
-            self.pitch_multiplier = 1.0;
+            self.combo = 0;
 
             // TODO: Make this more satisfying
             let mut time_offset = 5;
@@ -319,7 +299,14 @@ impl LevelState {
 
                 len_remaining -= 1;
                 // Destroy collected pegs.
-                let _ = world.insert_one(entity, Temporary(time_offset));
+                // `Temporary` counts rendered frames, but this `DelayedAction` counts
+                // fixed updates, so the board is the same at any framerate.
+                world.spawn((DelayedAction::new(
+                    move |world, _| {
+                        let _ = world.despawn(entity);
+                    },
+                    time_offset as f32 / 60.0,
+                ),));
 
                 if len_remaining > 10 {
                     time_offset += 2;
@@ -328,110 +315,16 @@ impl LevelState {
                 }
 
                 // Grow a child plant
-                let should_spawn_plant = world.get::<&Plant>(entity).map(|p| {
+                let seed = world.get::<&Plant>(entity).map(|plant| {
                     let peg_transform = world.get::<&Transform>(entity).unwrap();
-                    *peg_transform
+                    (peg_transform.position.xy(), plant.species)
                 });
 
-                if let Ok(transform) = should_spawn_plant {
-                    let p = transform.position.xy();
-
-                    fn plant_segment(
-                        world: &mut World,
-                        resources: &mut Resources,
-                        position: Vec2,
-                        stem_direction: Vec2,
-                        mut energy: usize,
-                        skip_stem: bool,
-                        mut gold_energy: usize,
-                    ) {
-                        if !skip_stem {
-                            if energy == 1 && gold_energy == 1 {
-                                spawn_gold(
-                                    world,
-                                    resources,
-                                    position,
-                                    stem_direction * PLANT_SEGMENT_LENGTH,
-                                );
-                            } else {
-                                if gold_energy > 0 && Random::new().f32() > 0.8 {
-                                    spawn_gold(
-                                        world,
-                                        resources,
-                                        position,
-                                        stem_direction * PLANT_SEGMENT_LENGTH,
-                                    );
-                                    gold_energy -= 1;
-                                } else {
-                                    spawn_plant(
-                                        world,
-                                        resources,
-                                        position,
-                                        stem_direction * PLANT_SEGMENT_LENGTH,
-                                    );
-                                }
-                            }
-                        }
-                        energy = energy.saturating_sub(1);
-                        if energy > 0 {
-                            world.spawn((DelayedAction::new(
-                                move |world, resources| {
-                                    let mut segment_count = 1;
-                                    let mut energy_in_segments = [energy, 0];
-
-                                    if Random::new().f32() > 0.8 {
-                                        segment_count += 1;
-                                        let transfer = Random::new().range_u32(1..energy as _);
-                                        energy_in_segments[0] -= transfer as usize;
-                                        energy_in_segments[1] += transfer as usize;
-                                    }
-
-                                    for i in 0..segment_count {
-                                        let range = std::f32::consts::PI * 0.4;
-                                        let rotation =
-                                            range * -1.0 + Random::new().f32() * range * 2.0;
-
-                                        let rotation = Quat::from_angle_axis(rotation, Vec3::Z);
-                                        let new_random_dir = rotation
-                                            .rotate_vector3(stem_direction.extend(0.0))
-                                            .xy();
-
-                                        let position =
-                                            position + new_random_dir * PLANT_SEGMENT_LENGTH;
-
-                                        plant_segment(
-                                            world,
-                                            resources,
-                                            position,
-                                            new_random_dir,
-                                            energy_in_segments[i],
-                                            false,
-                                            gold_energy,
-                                        );
-                                    }
-                                },
-                                0.2,
-                            ),));
-                        }
-                    }
-
-                    let range = std::f32::consts::PI * 0.5;
-                    let rotation = range * -1.0 + Random::new().f32() * range * 2.0;
-
-                    let rotation = Quat::from_angle_axis(rotation, Vec3::Z);
-                    let new_random_dir = rotation.rotate_vector3(Vec2::Y.extend(0.0)).xy();
-
+                if let Ok((position, species)) = seed {
                     world.spawn((DelayedAction::new(
                         move |world, resources| {
-                            plant_segment(
-                                world,
-                                resources,
-                                p,
-                                new_random_dir,
-                                Random::new().range_u32(3..20) as _,
-                                true,
-                                3,
-                            );
+                            let half_board = Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) / 2.0;
+                            grow_plant(world, resources, position, species, half_board)
                         },
                         0.01,
                     ),));
@@ -457,6 +350,10 @@ struct Eye {
 }
 
 struct GameAssets {
+    board_background: Handle<Material>,
+    shop_background: Handle<Material>,
+    witch_material: Handle<Material>,
+    pupil_material: Handle<Material>,
     stem_material: Handle<Material>,
     growable_plant_material: PegMaterial,
     plant_material: PegMaterial,
@@ -464,6 +361,8 @@ struct GameAssets {
     stone_material: PegMaterial,
     multiball_material: PegMaterial,
     multiplier_material: PegMaterial,
+    /// Shared by every `PegGlyph`.
+    glyph_material: Handle<Material>,
 
     //
     brick_material: Handle<Material>,
@@ -479,14 +378,18 @@ struct PegMaterial {
     base: Handle<Material>,
     glowing: Handle<Material>,
     shockwave: Handle<Material>,
+    /// The colors of `base`, `glowing` and `shockwave` in the manifest,
+    /// used by the Standard palette.
+    manifest_colors: [Color; 3],
 }
 
-const SHOT_POWER: f32 = 80.0;
+const SHOT_COST: i32 = 1;
 
 struct EyeFocalPoint;
 
+/// A `GrowablePlant` peg. Seeds without a species pick one when they grow.
 struct Plant {
-    last_direction: usize,
+    species: Option<PlantSpeciesId>,
 }
 
 fn run_eyes(world: &mut World, resources: &Resources) {
@@ -545,372 +448,253 @@ fn run_scale(world: &mut World, resources: &Resources) {
 
 struct MainCamera;
 
-fn main() {
-    App::default()
-        .with_resource(InitialSettings {
-            window_width: 1600,
-            window_height: 1200,
-            ..Default::default()
-        })
-        .setup_and_run(|world, resources| {
-            let rapier_integration = rapier_integration::RapierIntegration::new();
-
-            let view_height = 150.0;
-
-            let child_camera = world.spawn((
-                Transform::new(),
-                Camera {
-                    clear_color: Some(Color::BLACK),
-                    exposure: Exposure::EV100(6.0),
-                    projection_mode: ProjectionMode::Orthographic {
-                        height: view_height,
-                        z_near: -2.0,
-                        z_far: 2.0,
-                    },
-                    ..Default::default()
-                },
-                MainCamera,
-            ));
-            let parent = world.spawn((Transform::new().with_position(Vec3::Z * 2.0),));
-            let _ = world.set_parent(parent, child_camera);
-
-            let top_of_screen = Vec3::new(0.0, view_height / 2.0 * 0.75, 0.0);
-
-            // Spawn the background
-            {
-                let new_texture = resources.get::<AssetStore<Texture>>().load(
-                    "assets/BackgroundWide.png",
-                    koi_graphics_context::TextureSettings::default(),
-                );
-
-                let new_material = resources.get::<AssetStore<Material>>().add(Material {
-                    shader: Shader::UNLIT,
-                    base_color_texture: Some(new_texture),
-                    ..Default::default()
-                });
-
-                world.spawn((
-                    Transform::new().with_scale(Vec3::new(160.0 * 2.0, 160.0, 1.0)),
-                    Mesh::VERTICAL_QUAD,
-                    new_material,
-                ));
-            }
+const VIEW_HEIGHT: f32 = 150.0;
+/// The width of the board in the default 4:3 window.
+/// Gameplay uses this instead of the window's size so it plays the same in any window.
+const VIEW_WIDTH: f32 = VIEW_HEIGHT * 4.0 / 3.0;
 
-            let stem_material = resources.get::<AssetStore<Material>>().add(Material {
-                shader: Shader::UNLIT,
-                base_color: Color::from_srgb_hex(0x489B41, 1.0)
-                    .with_lightness(0.7)
-                    .with_chroma(0.3),
-                ..Default::default()
-            });
-
-            let recolor_shader = resources.get::<AssetStore<Shader>>().load(
-                "assets/custom_shader.glsl",
-                ShaderSettings {
-                    blending: Some((BlendFactor::One, BlendFactor::OneMinusSourceAlpha)),
-                    ..Default::default()
-                },
-            );
-
-            fn get_texture_material(
-                path: &str,
-                resources: &Resources,
-                shader: Handle<Shader>,
-                color: Color,
-            ) -> Handle<Material> {
-                let texture = resources
-                    .get::<AssetStore<Texture>>()
-                    .load(path, koi_graphics_context::TextureSettings::default());
-                resources.get::<AssetStore<Material>>().add(Material {
-                    shader,
-                    base_color_texture: Some(texture),
-                    base_color: color,
-                    ..Default::default()
-                })
-            }
+/// Where balls are fired from, just below the witch.
+fn shot_origin() -> Vec3 {
+    Vec3::new(0.0, VIEW_HEIGHT / 2.0 * 0.75, 0.0)
+}
 
-            fn load_peg_material(
-                resources: &mut Resources,
-                shader: &Handle<Shader>,
-                color: Color,
-            ) -> PegMaterial {
-                PegMaterial {
-                    glowing: get_texture_material(
-                        "assets/PegGlowing.png",
-                        resources,
-                        shader.clone(),
-                        color,
-                    ),
-                    shockwave: get_texture_material(
-                        "assets/PegShockWave.png",
-                        resources,
-                        shader.clone(),
-                        color,
-                    ),
-                    base: get_texture_material("assets/Peg.png", resources, shader.clone(), color),
-                }
-            }
+/// The gameplay part of a fixed update.
+/// Shared by the windowed game and the headless `Simulation`.
+fn run_gameplay(world: &mut World, resources: &mut Resources, time_step: f32) {
+    temporary::run_delayed_actions(world, resources, time_step);
+    run_path_movers(world, time_step);
 
-            let mut ui = ui::UI::new(world, resources);
+    run_balls(world, resources, -VIEW_HEIGHT / 2.0);
+    run_pegs(world, resources);
+    run_catchers(world, resources);
+    run_health(world, resources);
 
-            let spawn_witch = |world: &mut World| {
-                let witch_material = get_texture_material(
-                    "assets/Witch.png",
-                    resources,
-                    Shader::UNLIT_TRANSPARENT,
-                    Color::WHITE,
-                );
-                let pupil_material = get_texture_material(
-                    "assets/Pupil.png",
-                    resources,
-                    Shader::UNLIT_TRANSPARENT,
-                    Color::WHITE,
-                );
-
-                let witch = world.spawn((
-                    Transform::new()
-                        .with_position(top_of_screen + Vec3::Z * 0.2)
-                        .with_scale(Vec3::fill(35.0)),
-                    witch_material,
-                    Mesh::VERTICAL_QUAD,
-                ));
-
-                let witch_pupil_l = world.spawn((
-                    Transform::new().with_scale(Vec3::fill(0.07)),
-                    pupil_material.clone(),
-                    Mesh::VERTICAL_QUAD,
-                ));
-                let witch_pupil_r = world.spawn((
-                    Transform::new().with_scale(Vec3::fill(0.07)),
-                    pupil_material,
-                    Mesh::VERTICAL_QUAD,
-                ));
-
-                let witch_pupil_center_l = world.spawn((
-                    Transform::new().with_position(Vec3::new(-0.08, -0.14, 0.0)),
-                    Eye {
-                        radius: 0.03,
-                        range: f32::MAX,
-                        art: witch_pupil_l,
-                        other_eye: None,
-                    },
-                ));
-
-                let witch_pupil_center_r = world.spawn((
-                    Transform::new().with_position(Vec3::new(0.12, -0.14, 0.0)),
-                    Eye {
-                        radius: 0.03,
-                        range: f32::MAX,
-                        art: witch_pupil_r,
-                        other_eye: Some(witch_pupil_center_l),
-                    },
-                ));
-                world
-                    .get::<&mut Eye>(witch_pupil_center_l)
-                    .unwrap()
-                    .other_eye = Some(witch_pupil_center_r);
+    let rapier_integration = world
+        .query::<&mut RapierIntegration>()
+        .iter()
+        .next()
+        .unwrap()
+        .0;
+    let mut rapier_integration = world
+        .remove_one::<RapierIntegration>(rapier_integration)
+        .unwrap();
 
-                let _ = world.set_parent(witch, witch_pupil_center_l);
-                let _ = world.set_parent(witch, witch_pupil_center_r);
+    rapier_integration.gravity = Vec2::new(0.0, resources.get::<Tuning>().gravity);
+    rapier_integration.step(world);
+    world.spawn((rapier_integration,));
 
-                let _ = world.set_parent(witch_pupil_center_l, witch_pupil_l);
-                let _ = world.set_parent(witch_pupil_center_r, witch_pupil_r);
-            };
-            spawn_witch(world);
-
-            let mut shop_world = World::new();
-            let camera_child = shop_world.spawn((
-                Transform::new(),
-                Camera {
-                    clear_color: Some(Color::BLACK),
-                    exposure: Exposure::EV100(6.0),
-                    projection_mode: ProjectionMode::Orthographic {
-                        height: view_height,
-                        z_near: -2.0,
-                        z_far: 2.0,
-                    },
-                    ..Default::default()
-                },
-                MainCamera,
-            ));
-            let camera_parent = shop_world.spawn((Transform::new().with_position(Vec3::Z * 2.0),));
-            let _ = shop_world.set_parent(camera_parent, camera_child);
-
-            shop_world.spawn((Transform::new(), EyeFocalPoint, MouseFocalPoint));
-            shop_world.spawn((RapierIntegration::new(),));
-
-            spawn_witch(&mut shop_world);
-            ui.add_to_world(&mut shop_world);
-
-            // Spawn the shop background
-            {
-                let new_texture = resources.get::<AssetStore<Texture>>().load(
-                    "assets/ShopBackground.png",
-                    koi_graphics_context::TextureSettings::default(),
-                );
-
-                let new_material = resources.get::<AssetStore<Material>>().add(Material {
-                    shader: Shader::UNLIT,
-                    base_color_texture: Some(new_texture),
-                    ..Default::default()
-                });
+    check_for_defeat(resources);
+}
 
-                shop_world.spawn((
-                    Transform::new().with_scale(Vec3::new(160.0 * 2.0, 160.0, 1.0)),
-                    Mesh::VERTICAL_QUAD,
-                    new_material,
-                ));
+/// Runs a player input and records it if it had an effect.
+/// Played back inputs go through here too so a replay of a replay matches the original.
+fn apply_replay_event(world: &mut World, resources: &mut Resources, event: ReplayEvent) {
+    let applied = match event {
+        ReplayEvent::Shot { direction } => shoot(world, resources, direction),
+        ReplayEvent::ToggleShop => {
+            let mut level_state = resources.remove::<LevelState>().unwrap();
+
+            let ready_to_shoot = level_state.ready_to_shoot && !level_state.round_over;
+            if ready_to_shoot {
+                level_state.toggle_shop(world, resources);
             }
+            resources.add(level_state);
+            ready_to_shoot
+        }
+        ReplayEvent::Restart => {
+            let restartable = can_restart(resources);
+            restart_run(world, resources);
+            restartable
+        }
+        ReplayEvent::NextBoard => {
+            let round_over = resources.get::<LevelState>().round_over;
+            start_next_board(world, resources);
+            round_over
+        }
+        ReplayEvent::LoadRun => load_run(world, resources),
+    };
 
-            let ball_material = get_texture_material(
-                "assets/Ball.png",
-                resources,
-                recolor_shader.clone(),
-                Color::from_srgb_hex(0xd4af37, 1.0),
-            );
-
-            let peg_hit_sound = resources
-                .get::<AssetStore<Sound>>()
-                .load("assets/marimba.wav", Default::default());
+    if applied {
+        resources.get::<ReplayState>().record(event);
+    }
+}
 
-            world.spawn((Transform::new(), EyeFocalPoint, MouseFocalPoint));
+/// Fires a ball from the top of the screen if a shot is allowed right now.
+/// `direction` does not need to be normalized.
+fn shoot(world: &mut World, resources: &mut Resources, direction: Vec2) -> bool {
+    // Shoot one ball at a time
+    {
+        let mut level_state = resources.get::<LevelState>();
+        if !level_state.ready_to_shoot && !level_state.in_shop {
+            return false;
+        }
+        if level_state.round_over || level_state.game_over {
+            return false;
+        }
+    }
 
-            //world.spawn(ball_bundle);
+    {
+        if resources.get::<UIState>().incoming_gold > 0 {
+            return false;
+        }
+    }
+    if resources.get::<UIState>().gold < SHOT_COST {
+        return false;
+    }
 
-            let mut random = Random::new();
+    {
+        let mut level_state = resources.get::<LevelState>();
+        // Only once the shot is certain, so a refused shot doesn't lock out the next one.
+        level_state.ready_to_shoot = false;
+        level_state.fired_once = true;
+        level_state.shot_refunded = false;
+        if !level_state.in_shop {
+            if let Some(balls_remaining) = &mut level_state.balls_remaining {
+                *balls_remaining = balls_remaining.saturating_sub(1);
+            }
+        }
+    }
+    resources.get::<UIState>().gold -= SHOT_COST;
 
-            world.spawn((rapier_integration,));
+    let dir = direction.normalized() * resources.get::<Tuning>().shot_power;
 
-            let mut pointer_position = Vec3::ZERO;
+    let ball = next_ball_settings(resources);
+    {
+        let mut level_state = resources.get::<LevelState>();
+        if !level_state.in_shop {
+            level_state.effects_to_apply_to_next_ball.clear();
+        }
+    }
 
-            let growable_plant_material =
-                load_peg_material(resources, &recolor_shader, Color::ORANGE);
-            let plant_material = load_peg_material(resources, &recolor_shader, Color::GREEN);
-            let gold_material = load_peg_material(resources, &recolor_shader, Color::YELLOW);
-            let stone_material =
-                load_peg_material(resources, &recolor_shader, Color::BLACK.with_lightness(0.6));
-            let multiball_material = load_peg_material(
-                resources,
-                &recolor_shader,
-                Color::PURPLE.with_lightness(0.6),
-            );
-            let multiplier_material = load_peg_material(
-                resources,
-                &recolor_shader,
-                Color::ELECTRIC_INDIGO.with_lightness(0.9),
-            );
-
-            let brick_material = get_texture_material(
-                "assets/Brick.png",
-                resources,
-                recolor_shader.clone(),
-                Color::BLACK.with_lightness(0.6),
-            );
-            let plus_one = get_texture_material(
-                "assets/+1.png",
-                resources,
-                Shader::UNLIT_TRANSPARENT,
-                Color::WHITE,
-            );
-            let plus_twenty = get_texture_material(
-                "assets/+20.png",
-                resources,
-                Shader::UNLIT_TRANSPARENT,
-                Color::WHITE,
-            );
-            let x2 = get_texture_material(
-                "assets/x2.png",
-                resources,
-                recolor_shader.clone(),
-                Color::WHITE,
-            );
-            resources.add(GameAssets {
-                stem_material,
-                growable_plant_material,
-                plant_material,
-                gold_material,
-                stone_material: stone_material,
-                brick_material,
-                multiball_material,
-                ball_material,
-                plus_one,
-                plus_twenty,
-                multiplier_material,
-                x2,
-            });
-
-            // apply_rock_storm(world, resources, P);
-            for _ in 0..2 {
-                spawn_peg(
-                    world,
-                    resources,
-                    Vec2::new(random.range_f32(-50.0..50.0), random.range_f32(-50.0..-20.)),
-                    PegType::GrowablePlant,
-                );
-            }
+    spawn_ball(
+        world,
+        resources,
+        shot_origin().xy(),
+        dir,
+        ball.health_subtract_rate,
+        ball.size,
+    );
+    true
+}
 
-            for _ in 0..2 {
-                spawn_peg(
-                    world,
-                    resources,
-                    Vec2::new(random.range_f32(-50.0..50.0), random.range_f32(-50.0..10.)),
-                    PegType::MultiBall,
-                );
-            }
+/// The ball the next shot will fire, including any powerups waiting for it.
+fn next_ball_settings(resources: &Resources) -> BallSettings {
+    let level_state = resources.get::<LevelState>();
+    let registry = resources.get::<PowerupRegistry>();
 
-            for _ in 0..2 {
-                spawn_peg(
-                    world,
-                    resources,
-                    Vec2::new(random.range_f32(-50.0..50.0), random.range_f32(-50.0..10.)),
-                    PegType::Multiplier,
-                );
-            }
+    let mut ball = BallSettings {
+        size: resources.get::<Tuning>().ball_size,
+        health_subtract_rate: 1.0,
+        preview_bounces: level_state.aim_preview_bounces,
+    };
+    if !level_state.in_shop {
+        for id in &level_state.effects_to_apply_to_next_ball {
+            registry.get(*id).apply_to_ball(&mut ball);
+        }
+    }
+    ball
+}
 
-            for _ in 0..5 {
-                spawn_peg(
-                    world,
-                    resources,
-                    Vec2::new(random.range_f32(-50.0..50.0), random.range_f32(-50.0..10.)),
-                    PegType::Stone,
-                );
-            }
+/// Predicts the path of a ball, bouncing off pegs and bricks up to `bounces` times.
+/// Returns points along the path, ending a short way past the last bounce.
+fn predict_shot(
+    rapier_integration: &RapierIntegration,
+    tuning: &Tuning,
+    origin: Vec2,
+    mut velocity: Vec2,
+    radius: f32,
+    bounces: usize,
+    time_after_last_bounce: f32,
+) -> Vec<Vec2> {
+    const TIME_STEP: f32 = 1.0 / 60.0;
+    const MAX_TIME: f32 = 2.0;
+
+    let mut points = Vec::new();
+    let mut p = origin;
+    let mut bounces_remaining = bounces;
+    let mut time = 0.0;
+    let mut time_remaining = if bounces == 0 {
+        time_after_last_bounce
+    } else {
+        MAX_TIME
+    };
 
-            for i in 0..3 {
-                if let Some(p) = select_powerup(&mut shop_world) {
-                    spawn_brick_with_powerup(
-                        &mut shop_world,
-                        resources,
-                        Vec2::new(i as f32 * 65.0 - 60.0, -10.0),
-                        p,
-                    );
+    while time_remaining > 0.0 && time < MAX_TIME {
+        let hit = if bounces_remaining > 0 {
+            rapier_integration.cast_ball(p, velocity * TIME_STEP, radius, 1.0, None)
+        } else {
+            None
+        };
+
+        match hit {
+            Some(hit) if velocity.dot(hit.normal) < 0.0 => {
+                p += velocity * TIME_STEP * hit.time_of_impact;
+                velocity -= hit.normal * velocity.dot(hit.normal) * (1.0 + tuning.restitution);
+                bounces_remaining -= 1;
+                if bounces_remaining == 0 {
+                    time_remaining = time_after_last_bounce;
                 }
             }
+            _ => p += velocity * TIME_STEP,
+        }
+        velocity += Vec2::Y * tuning.gravity * TIME_STEP;
 
-            for i in 0..2 {
-                if let Some(p) = select_powerup(&mut shop_world) {
-                    spawn_brick_with_powerup(
-                        &mut shop_world,
-                        resources,
-                        Vec2::new(i as f32 * 70.0 - 40.0, -50.0),
-                        p,
-                    );
-                }
-            }
+        points.push(p);
+        time += TIME_STEP;
+        time_remaining -= TIME_STEP;
+    }
+    points
+}
 
-            apply_rock_storm(
-                world,
-                resources,
-                PegType::Gold,
-                0.4,
-                0.0,
-                4,
-                Vec2::new(0.0, -20.0),
-                60.0,
-            );
-
-            let level_state = LevelState::new(shop_world);
-            resources.add(level_state);
+fn main() {
+    if run_simulation_from_command_line() {
+        return;
+    }
+
+    App::default()
+        .with_resource(InitialSettings {
+            window_width: 1600,
+            window_height: 1200,
+            ..Default::default()
+        })
+        .setup_and_run(|world, resources| {
+            let playback = replay_from_command_line();
+            let playing_back = playback.is_some();
+            let (seed, level_path) = match &playback {
+                Some(replay) => (replay.seed, replay.level_path.clone()),
+                None => (seed_from_command_line(), level_from_command_line()),
+            };
+            println!("Seed: {}", seed);
+            resources.add(GameRandom::new(seed));
+            resources.add(PowerupRegistry::load());
+            resources.add(Tuning::load());
+            resources.add(PlantSpeciesRegistry::load());
+            resources.add(Settings::load());
+            resources.add(AssetManifest::load());
+            resources.add(MenuState::new(if playing_back {
+                Screen::Playing
+            } else {
+                Screen::MainMenu
+            }));
+            resources.add(ReplayState::new(Replay::new(seed, &level_path), playback));
+
+            let game_assets = GameAssets::load(resources);
+            resources.add(game_assets);
+            start_new_run(world, resources, &level_path);
+
+            let mut ui = ui::UI::new(world, resources);
+            ui.add_to_world(&mut resources.get::<LevelState>().other_world);
+
+            let top_of_screen = shot_origin();
+
+            let mut audio = Audio::load(resources);
+
+            #[cfg(not(target_arch = "wasm32"))]
+            let mut hot_reload = HotReload::new();
+
+            let mut random = Random::new();
+
+            let mut pointer_position = Vec3::ZERO;
 
             let mut subtract_gold_timer = 1.0;
 
@@ -919,37 +703,64 @@ fn main() {
             // See [koi::Event]
             move |event, world, resources| match event {
                 Event::FixedUpdate => {
+                    let time_step = resources.get::<Time>().fixed_time_step_seconds as f32;
+                    audio.run_music(resources, time_step);
+
+                    if resources.get::<MenuState>().is_paused() {
+                        return;
+                    }
+
                     {
                         let mut ui_state = resources.get::<UIState>();
                         let level_state = resources.get::<LevelState>();
                         ui_state.ball_active = level_state.ready_to_shoot;
+                        ui_state.balls_remaining = level_state.balls_remaining;
                     }
 
-                    temporary::run_delayed_actions(world, resources);
+                    // Counting up gold happens here instead of in `Draw`
+                    // so that when shots are allowed doesn't depend on the framerate.
+                    {
+                        let mut ui_state = resources.get::<UIState>();
+                        if ui_state.incoming_gold > 0 {
+                            if subtract_gold_timer < 0.0 {
+                                let gold_change_speed = (ui_state.incoming_gold / 50)
+                                    .max(1)
+                                    .min(ui_state.incoming_gold);
 
-                    // Update the shot visual
+                                ui_state.incoming_gold -= gold_change_speed;
+                                ui_state.gold += gold_change_speed;
 
-                    run_balls(world, resources, -view_height / 2.0);
-                    run_pegs(world, resources, &peg_hit_sound);
-                    run_health(world, resources);
+                                subtract_gold_timer = 0.01;
+                                //level_state.screen_shake_amount += 0.01;
+                            } else {
+                                subtract_gold_timer -= 1.0 / 60.0;
+                            }
+                        } else {
+                            subtract_gold_timer = 1.0;
+                        }
+                    }
 
-                    let rapier_integration = world
-                        .query::<&mut RapierIntegration>()
-                        .iter()
-                        .next()
-                        .unwrap()
-                        .0;
-                    let mut rapier_integration = world
-                        .remove_one::<RapierIntegration>(rapier_integration)
-                        .unwrap();
+                    let replay_events = resources.get::<ReplayState>().events_for_tick();
+                    for event in replay_events {
+                        apply_replay_event(world, resources, event);
+                    }
 
-                    rapier_integration.step(world);
-                    world.spawn((rapier_integration,));
+                    run_gameplay(world, resources, time_step);
+                    run_peg_hit_effects(world, resources, &audio);
+                    resources.get::<ReplayState>().tick += 1;
+                    update_screen(resources);
 
                     let victory = resources.get::<LevelState>().victory;
-                    if !victory && resources.get::<UIState>().gold >= 1000 {
-                        resources.get::<UIState>().current_text =
-                            "You've reached 1000 and won!".into();
+                    let won = {
+                        let ui_state = resources.get::<UIState>();
+                        ui_state.gold >= ui_state.win_gold
+                    };
+                    if !victory && won {
+                        {
+                            let mut ui_state = resources.get::<UIState>();
+                            ui_state.current_text =
+                                format!("You've reached {} and won!", ui_state.win_gold);
+                        }
                         resources.get::<LevelState>().victory = true;
                         apply_rock_storm(
                             world,
@@ -985,50 +796,59 @@ fn main() {
                         );
                     }
                 }
+                Event::KappEvent(KappEvent::KeyDown { key, .. })
+                    if menu_key_down(world, resources, *key) => {}
                 Event::KappEvent(KappEvent::KeyDown { key: Key::S, .. }) => {
-                    let mut level_state = resources.remove::<LevelState>().unwrap();
-
-                    if level_state.ready_to_shoot {
-                        level_state.toggle_shop(world, resources);
+                    if !resources.get::<ReplayState>().playing_back() {
+                        apply_replay_event(world, resources, ReplayEvent::ToggleShop);
+                    }
+                }
+                Event::KappEvent(KappEvent::KeyDown {
+                    key: Key::Space, ..
+                }) => {
+                    if !resources.get::<ReplayState>().playing_back() {
+                        apply_replay_event(world, resources, ReplayEvent::NextBoard);
+                    }
+                }
+                Event::KappEvent(KappEvent::KeyDown { key: Key::R, .. }) => {
+                    if !resources.get::<ReplayState>().playing_back() {
+                        apply_replay_event(world, resources, ReplayEvent::Restart);
+                    }
+                }
+                Event::KappEvent(KappEvent::KeyDown { key: Key::F5, .. }) => {
+                    if !resources.get::<ReplayState>().playing_back() {
+                        save_run(world, resources);
+                    }
+                }
+                Event::KappEvent(KappEvent::KeyDown { key: Key::F9, .. }) => {
+                    if !resources.get::<ReplayState>().playing_back() {
+                        apply_replay_event(world, resources, ReplayEvent::LoadRun);
                     }
-                    resources.add(level_state);
                 }
                 Event::KappEvent(KappEvent::KeyDown { key: Key::O, .. }) => {
                     // Toggle screen shake
-                    let mut level_state = resources.get::<LevelState>();
-                    level_state.screen_shake_enabled = !level_state.screen_shake_enabled;
+                    let mut settings = resources.get::<Settings>();
+                    settings.screen_shake = if settings.screen_shake > 0.0 {
+                        0.0
+                    } else {
+                        1.0
+                    };
+                    settings.save();
                 }
                 Event::Draw => {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    hot_reload.run(world, resources, &mut audio);
+
                     {
                         let mut level_state = resources.get::<LevelState>();
 
-                        let mut ui_state = resources.get::<UIState>();
-                        if ui_state.incoming_gold > 0 {
-                            if subtract_gold_timer < 0.0 {
-                                let gold_change_speed = (ui_state.incoming_gold / 50)
-                                    .max(1)
-                                    .min(ui_state.incoming_gold);
-
-                                ui_state.incoming_gold -= gold_change_speed;
-                                ui_state.gold += gold_change_speed;
-
-                                subtract_gold_timer = 0.01;
-                                //level_state.screen_shake_amount += 0.01;
-                            } else {
-                                subtract_gold_timer -= 1.0 / 60.0;
-                            }
-                        } else {
-                            subtract_gold_timer = 1.0;
-                        }
-
                         let mut screen_shake_amount = level_state.screen_shake_amount;
 
-                        if !level_state.screen_shake_enabled {
-                            screen_shake_amount = 0.0;
-                        }
+                        let shake_range =
+                            screen_shake_amount * resources.get::<Settings>().screen_shake;
                         let screen_shake = Vec2::new(
-                            random.range_f32(-screen_shake_amount..screen_shake_amount),
-                            random.range_f32(-screen_shake_amount..screen_shake_amount),
+                            random.range_f32(-shake_range..shake_range),
+                            random.range_f32(-shake_range..shake_range),
                         );
 
                         let mut q = world.query::<(&mut Transform, &MainCamera)>();
@@ -1048,11 +868,18 @@ fn main() {
                     {
                         return;
                     }
+                    {
+                        let menu_text = resources
+                            .get::<MenuState>()
+                            .text(&resources.get::<Settings>());
+                        resources.get::<UIState>().menu_text = menu_text;
+                    }
                     ui.run(world, resources);
                     draw_screen_space_uis(world, resources);
 
                     run_eyes(world, resources);
                     run_scale(world, resources);
+                    despawn_orphaned_glyphs(world);
                     temporary::despawn_temporaries(world);
 
                     let input = resources.get::<Input>();
@@ -1083,27 +910,43 @@ fn main() {
                         .1
                         .position = pointer_position;
 
-                    let dir = (pointer_position - top_of_screen).normalized();
-
-                    let velocity = dir * SHOT_POWER;
+                    let hovered_peg = {
+                        let mut q = world.query::<&RapierIntegration>();
+                        let rapier_integration = q.iter().next().unwrap().1;
+                        rapier_integration
+                            .entities_at_point(pointer_position.xy())
+                            .into_iter()
+                            .find_map(|e| world.get::<&Peg>(e).ok().map(|peg| peg.peg_type))
+                    };
+                    resources.get::<UIState>().hovered_text =
+                        hovered_peg.map(|peg_type| peg_type.description().to_string());
 
-                    let mut v = velocity;
-                    let mut p = top_of_screen;
+                    let dir = (pointer_position - top_of_screen).normalized();
 
-                    let steps = 10;
-                    let step_length = 0.3 / steps as f32;
+                    let velocity = dir * resources.get::<Tuning>().shot_power;
 
-                    for _ in 0..steps {
-                        p += v * step_length;
-                        v += Vec3::Y * GRAVITY * step_length;
+                    if resources.get::<MenuState>().is_paused() {
+                        return;
+                    }
 
-                        p.z = 0.2;
+                    let ball = next_ball_settings(resources);
+                    let points = predict_shot(
+                        world.query::<&RapierIntegration>().iter().next().unwrap().1,
+                        &resources.get::<Tuning>(),
+                        top_of_screen.xy(),
+                        velocity.xy(),
+                        ball.size.min(50.0) / 2.0,
+                        ball.preview_bounces,
+                        resources.get::<Settings>().aim_preview.seconds(),
+                    );
 
+                    // Space the dots out like the original ten over the first 0.3 seconds.
+                    for p in points.iter().skip(1).step_by(2) {
                         world.spawn((
                             Temporary(2),
                             Mesh::VERTICAL_CIRCLE,
                             Material::UNLIT,
-                            Transform::new().with_position(p),
+                            Transform::new().with_position(p.extend(0.2)),
                         ));
                     }
                 }
@@ -1114,28 +957,6 @@ fn main() {
                     button: PointerButton::Primary,
                     ..
                 }) => {
-                    // Shoot one ball at a time
-                    {
-                        let mut level_state = resources.get::<LevelState>();
-                        if !level_state.ready_to_shoot && !level_state.in_shop {
-                            return;
-                        }
-                        level_state.ready_to_shoot = false;
-                    }
-
-                    {
-                        if resources.get::<UIState>().incoming_gold > 0 {
-                            return;
-                        }
-                    }
-                    if resources.get::<LevelState>().in_shop && resources.get::<UIState>().gold < 0
-                    {
-                        return;
-                    }
-
-                    resources.get::<LevelState>().fired_once = true;
-                    resources.get::<UIState>().gold -= 1;
-
                     pointer_position = {
                         let mut q = world.query::<(&mut GlobalTransform, &Camera, &MainCamera)>();
                         let mut i = q.iter();
@@ -1152,37 +973,12 @@ fn main() {
                         ray.origin
                     };
 
-                    let dir = (pointer_position - top_of_screen).normalized() * SHOT_POWER;
-
-                    let mut ball_size = 3.5;
-                    let mut health_subtract_rate = 1.0;
-
+                    if !resources.get::<ReplayState>().playing_back()
+                        && !resources.get::<MenuState>().is_paused()
                     {
-                        let mut level_state = resources.get::<LevelState>();
-
-                        if !level_state.in_shop {
-                            for effect in level_state.effects_to_apply_to_next_ball.drain(..) {
-                                match effect {
-                                    Effects::BigBall => {
-                                        ball_size *= 2.0;
-                                        health_subtract_rate *= 2.0;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
+                        let direction = (pointer_position - top_of_screen).xy();
+                        apply_replay_event(world, resources, ReplayEvent::Shot { direction });
                     }
-
-                    let p = top_of_screen;
-
-                    spawn_ball(
-                        world,
-                        resources,
-                        p.xy(),
-                        dir.xy(),
-                        health_subtract_rate,
-                        ball_size,
-                    );
                 }
                 _ => {}
             }
@@ -1207,7 +1003,6 @@ fn spawn_ball(
         .remove_one::<RapierIntegration>(rapier_integration)
         .unwrap();
 
-    let mut assets = resources.get::<GameAssets>();
     let mut health_subtract_rate = 1.0;
 
     ball_size = ball_size.min(50.0);
@@ -1217,7 +1012,7 @@ fn spawn_ball(
             .linvel([dir.x, dir.y].into())
             .build(),
         ColliderBuilder::ball(ball_size / 2.0)
-            .restitution(0.7)
+            .restitution(resources.get::<Tuning>().restitution)
             .build(),
     );
     let position = position.extend(0.3);
@@ -1226,30 +1021,39 @@ fn spawn_ball(
         Transform::new()
             .with_position(position)
             .with_scale(Vec3::fill(ball_size)),
-        Mesh::VERTICAL_QUAD,
-        assets.ball_material.clone(),
         Ball {
             health_subtract_rate,
+            touching: Vec::new(),
         },
         EyeFocalPoint,
         rapier_handle,
     );
 
-    world.spawn(b);
+    let ball = world.spawn(b);
+    if let Some(assets) = resources.try_get::<GameAssets>() {
+        let _ = world.insert(ball, (Mesh::VERTICAL_QUAD, assets.ball_material.clone()));
+    }
     world.spawn((rapier_integration,));
 }
 
 #[derive(Clone)]
 struct Ball {
     health_subtract_rate: f32,
+    /// Entities this ball is currently in contact with.
+    touching: Vec<Entity>,
 }
 
 #[derive(Clone)]
 struct Peg {
     hit: bool,
+    peg_type: PegType,
+}
+
+/// Only present on pegs spawned while `GameAssets` are loaded.
+#[derive(Clone)]
+struct PegVisuals {
     shockwave_child: Entity,
     glowing_material: Handle<Material>,
-    peg_type: PegType,
 }
 
 #[derive(Clone)]
@@ -1285,10 +1089,30 @@ fn run_balls(world: &mut World, resources: &mut Resources, world_bottom: f32) {
 
     if count == 0 {
         let mut level_state = resources.get::<LevelState>();
+        let pegs_hit = level_state.collected_pegs.len();
+        let multiplier = level_state.multiplier;
         let new_gold = level_state.prepare_to_shoot(world);
 
         if level_state.fired_once {
             level_state.fired_once = false;
+
+            if !level_state.in_shop {
+                let gold_earned = (new_gold as f32 * multiplier) as i32;
+                level_state
+                    .round_stats
+                    .add_shot(pegs_hit, gold_earned, multiplier);
+                level_state
+                    .run_stats
+                    .add_shot(pegs_hit, gold_earned, multiplier);
+
+                if level_state.balls_remaining == Some(0) {
+                    level_state.round_over = true;
+                    level_state.boards_played += 1;
+                    resources.get::<UIState>().round_summary =
+                        Some(level_state.round_stats.summary_text());
+                }
+            }
+
             if new_gold == 0 {
                 resources.get::<UIState>().current_text = ":(".into();
             }
@@ -1310,9 +1134,15 @@ fn run_balls(world: &mut World, resources: &mut Resources, world_bottom: f32) {
             if new_gold > 200 {
                 resources.get::<UIState>().current_text = ":O :O :O!!!!".into();
             }
+            if level_state.shot_refunded {
+                resources.get::<UIState>().current_text += " Free ball!";
+            }
         }
-        if resources.get::<UIState>().gold >= 1000 {
-            resources.get::<UIState>().current_text = "YOU WIN!!!".into();
+        {
+            let mut ui_state = resources.get::<UIState>();
+            if ui_state.gold >= ui_state.win_gold {
+                ui_state.current_text = "YOU WIN!!!".into();
+            }
         }
 
         resources.get::<UIState>().incoming_gold +=
@@ -1322,196 +1152,171 @@ fn run_balls(world: &mut World, resources: &mut Resources, world_bottom: f32) {
     }
 }
 
-fn run_pegs(world: &mut World, resources: &mut Resources, peg_hit_sound: &Handle<Sound>) {
+/// Despawns balls that fall into a `Catcher` and refunds the shot.
+fn run_catchers(world: &mut World, resources: &mut Resources) {
+    let contact_events = world
+        .query::<&RapierIntegration>()
+        .iter()
+        .next()
+        .unwrap()
+        .1
+        .contact_events
+        .clone();
+
+    for event in contact_events {
+        if event.kind != ContactEventKind::IntersectionStarted {
+            continue;
+        }
+        let ball = if world.get::<&Catcher>(event.entity_a).is_ok() {
+            event.entity_b
+        } else if world.get::<&Catcher>(event.entity_b).is_ok() {
+            event.entity_a
+        } else {
+            continue;
+        };
+        if world.get::<&Ball>(ball).is_err() {
+            continue;
+        }
+        let _ = world.despawn(ball);
+
+        let mut level_state = resources.get::<LevelState>();
+        if !level_state.shot_refunded {
+            level_state.shot_refunded = true;
+            if let Some(balls_remaining) = &mut level_state.balls_remaining {
+                *balls_remaining += 1;
+            }
+            let mut ui_state = resources.get::<UIState>();
+            ui_state.gold += SHOT_COST;
+            ui_state.current_text = "Free ball!".into();
+        }
+    }
+}
+
+/// Recorded by `run_pegs` when a ball first hits a peg.
+/// The windowed game turns these into sounds and popups in `run_peg_hit_effects`.
+#[derive(Clone)]
+struct PegHit {
+    entity: Entity,
+    peg_type: PegType,
+    /// Where the ball touched the peg, so popups appear at the impact.
+    position: Vec3,
+    combo: usize,
+    /// How hard the ball hit, from `ContactEvent::impulse`.
+    impulse: f32,
+}
+
+fn run_pegs(world: &mut World, resources: &mut Resources) {
     let mut to_despawn = Vec::new();
     let mut deferred_actions = Vec::new();
     {
         {
-            let rapier_integration = world
-                .query::<&mut RapierIntegration>()
+            let contact_events = world
+                .query::<&RapierIntegration>()
                 .iter()
                 .next()
                 .unwrap()
-                .0;
-            let rapier_integration = world
-                .remove_one::<RapierIntegration>(rapier_integration)
-                .unwrap();
-
-            let sounds = resources.get::<AssetStore<Sound>>();
-            let mut audio_manager = resources.get::<AudioManager>();
-
-            let peg_hit_sound = sounds.get(peg_hit_sound);
+                .1
+                .contact_events
+                .clone();
 
             let mut level_state = resources.get::<LevelState>();
 
-            for (e, (transform, ball)) in world.query::<(&Transform, &mut Ball)>().iter() {
-                let collider = world.get::<&RapierRigidBody>(e).unwrap();
-                for contact_pair in rapier_integration
-                    .narrow_phase
-                    .contacts_with(collider.collider_handle)
-                {
-                    if !contact_pair.has_any_active_contact {
+            for event in contact_events {
+                let (e, entity) = if world.get::<&Ball>(event.entity_a).is_ok() {
+                    (event.entity_a, event.entity_b)
+                } else if world.get::<&Ball>(event.entity_b).is_ok() {
+                    (event.entity_b, event.entity_a)
+                } else {
+                    continue;
+                };
+
+                match event.kind {
+                    ContactEventKind::Started => {
+                        world.get::<&mut Ball>(e).unwrap().touching.push(entity);
+                    }
+                    ContactEventKind::Stopped => {
+                        world
+                            .get::<&mut Ball>(e)
+                            .unwrap()
+                            .touching
+                            .retain(|t| *t != entity);
                         continue;
                     }
+                    _ => continue,
+                }
 
-                    let other_collider = if contact_pair.collider1 == collider.collider_handle {
-                        contact_pair.collider2
-                    } else {
-                        contact_pair.collider1
-                    };
-
-                    let user_data = rapier_integration
-                        .collider_set
-                        .get(other_collider)
-                        .unwrap()
-                        .user_data;
-
-                    if user_data != 0 {
-                        let entity = Entity::from_bits(user_data as _).unwrap();
-
-                        if let Ok(mut peg) = world.get::<&mut Peg>(entity) {
-                            if !peg.hit {
-                                peg.hit = true;
-                                *world.get::<&mut Handle<Material>>(entity).unwrap() =
-                                    peg.glowing_material.clone();
-
-                                level_state.pitch_multiplier += 0.1;
-                                level_state.pitch_multiplier =
-                                    level_state.pitch_multiplier.min(10.0);
-
-                                world.get::<&mut Scale>(peg.shockwave_child).unwrap().t = 0.5;
-                                level_state.collected_pegs.push(entity);
-
-                                match peg.peg_type {
-                                    PegType::Plant | PegType::Gold => audio_manager
-                                        .play_one_shot_with_speed(
-                                            peg_hit_sound,
-                                            level_state.pitch_multiplier,
-                                        ),
-                                    PegType::Multiplier => {
-                                        let position =
-                                            world.get::<&GlobalTransform>(entity).unwrap().position;
-                                        deferred_actions.push(DelayedAction::new(
-                                            move |world, resources| {
-                                                world.spawn((
-                                                    Transform::new()
-                                                        .with_position(
-                                                            position - Vec2::fill(2.0).extend(0.0),
-                                                        )
-                                                        .with_scale(Vec3::fill(20.0)),
-                                                    resources.get::<GameAssets>().x2.clone(),
-                                                    Mesh::VERTICAL_QUAD,
-                                                    Temporary(90),
-                                                ));
-                                            },
-                                            0.01,
-                                        ));
-                                        level_state.multiplier *= 2.0;
-                                        audio_manager.play_one_shot_with_speed(peg_hit_sound, 8.0);
-                                    }
-                                    _ => {
-                                        audio_manager.play_one_shot_with_speed(peg_hit_sound, 0.7);
-                                    }
-                                }
-                                match peg.peg_type {
-                                    PegType::Gold => {
-                                        let position =
-                                            world.get::<&GlobalTransform>(entity).unwrap().position;
-                                        deferred_actions.push(DelayedAction::new(
-                                            move |world, resources| {
-                                                world.spawn((
-                                                    Transform::new()
-                                                        .with_position(
-                                                            position + Vec2::fill(2.0).extend(0.0),
-                                                        )
-                                                        .with_scale(Vec3::fill(15.0)),
-                                                    resources
-                                                        .get::<GameAssets>()
-                                                        .plus_twenty
-                                                        .clone(),
-                                                    Mesh::VERTICAL_QUAD,
-                                                    Temporary(50),
-                                                ));
-                                            },
-                                            0.01,
-                                        ));
-
-                                        level_state.screen_shake_amount += 0.3;
-                                    }
-                                    PegType::Plant => {
-                                        let position =
-                                            world.get::<&GlobalTransform>(entity).unwrap().position;
-                                        deferred_actions.push(DelayedAction::new(
-                                            move |world, resources| {
-                                                world.spawn((
-                                                    Transform::new()
-                                                        .with_position(
-                                                            position + Vec2::fill(2.0).extend(0.0),
-                                                        )
-                                                        .with_scale(Vec3::fill(12.0)),
-                                                    resources.get::<GameAssets>().plus_one.clone(),
-                                                    Mesh::VERTICAL_QUAD,
-                                                    Temporary(30),
-                                                ));
-                                            },
-                                            0.01,
-                                        ));
-
-                                        level_state.screen_shake_amount += 0.05;
-                                    }
-                                    PegType::MultiBall => {
-                                        level_state.screen_shake_amount += 0.1;
-                                        let position =
-                                            world.get::<&GlobalTransform>(entity).unwrap().position;
-
-                                        deferred_actions.push(DelayedAction::new(
-                                            move |world, resources| {
-                                                let _ = world.despawn(entity);
-                                                spawn_ball(
-                                                    world,
-                                                    resources,
-                                                    position.xy(),
-                                                    Vec2::ZERO,
-                                                    1.0,
-                                                    3.5,
-                                                );
-                                            },
-                                            0.01,
-                                        ))
-                                    }
-                                    _ => {}
-                                }
+                if let Ok(mut peg) = world.get::<&mut Peg>(entity) {
+                    if !peg.hit {
+                        peg.hit = true;
+
+                        level_state.combo += 1;
+
+                        level_state.collected_pegs.push(entity);
+                        level_state.round_stats.add_peg_hit(peg.peg_type);
+                        level_state.run_stats.add_peg_hit(peg.peg_type);
+
+                        let position = world.get::<&Transform>(entity).unwrap().position;
+                        let combo = level_state.combo;
+                        level_state.peg_hits.push(PegHit {
+                            entity,
+                            peg_type: peg.peg_type,
+                            position: event
+                                .point
+                                .map_or(position, |point| point.extend(position.z)),
+                            combo,
+                            impulse: event.impulse,
+                        });
+
+                        match peg.peg_type {
+                            PegType::Multiplier => {
+                                level_state.multiplier *= 2.0;
                             }
+                            PegType::MultiBall => deferred_actions.push(DelayedAction::new(
+                                move |world, resources| {
+                                    let _ = world.despawn(entity);
+                                    spawn_ball(
+                                        world,
+                                        resources,
+                                        position.xy(),
+                                        Vec2::ZERO,
+                                        1.0,
+                                        resources.get::<Tuning>().ball_size,
+                                    );
+                                },
+                                0.01,
+                            )),
+                            _ => {}
                         }
+                    }
+                }
 
-                        if let Ok(mut powerup) = world.get::<&mut Powerup>(entity) {
-                            level_state.screen_shake_amount += 0.8;
+                if let Ok(mut powerup) = world.get::<&mut Powerup>(entity) {
+                    level_state.screen_shake_amount += 0.8;
 
-                            if powerup.cost < 0 {
-                                resources.get::<UIState>().gold -= powerup.cost;
-                            }
-                            powerup.cost -= 1;
-
-                            // Acquire power up
-                            if powerup.cost <= 0 && powerup.cost != -20 {
-                                powerup.cost = -20;
-                                to_despawn.push(entity);
-                                level_state
-                                    .effects_to_apply_to_next_ball
-                                    .push(powerup.effect.clone());
-                                // Quit shop
-                            }
-                            to_despawn.push(e);
-                        }
-                        // Remove health as this ball touches the peg to prevent it from getting stuck.
-                        if let Ok(mut health) = world.get::<&mut Health>(entity) {
-                            health.0 -= ball.health_subtract_rate / 60.0;
-                        }
+                    if powerup.cost < 0 {
+                        resources.get::<UIState>().gold -= powerup.cost;
+                    }
+                    powerup.cost -= 1;
+
+                    // Acquire power up
+                    if powerup.cost <= 0 && powerup.cost != -20 {
+                        powerup.cost = -20;
+                        to_despawn.push(entity);
+                        level_state.effects_to_apply_to_next_ball.push(powerup.id);
+                        // Quit shop
                     }
+                    to_despawn.push(e);
                 }
             }
 
-            world.spawn((rapier_integration,));
+            // Remove health as balls touch pegs to prevent them from getting stuck.
+            for (_, ball) in world.query::<&Ball>().iter() {
+                for entity in &ball.touching {
+                    if let Ok(mut health) = world.get::<&mut Health>(*entity) {
+                        health.0 -= ball.health_subtract_rate / 60.0;
+                    }
+                }
+            }
         }
 
         for deferred_action in deferred_actions {
@@ -1525,7 +1330,7 @@ fn run_pegs(world: &mut World, resources: &mut Resources, peg_hit_sound: &Handle
                 world.spawn((DelayedAction::new(
                     Box::new(move |world: &mut World, resources: &mut Resources| {
                         // Replacement powerup
-                        if let Some(p) = select_powerup(world) {
+                        if let Some(p) = select_powerup(world, resources) {
                             spawn_brick_with_powerup(world, resources, t.xy(), p);
                         }
                     }),
@@ -1538,27 +1343,117 @@ fn run_pegs(world: &mut World, resources: &mut Resources, peg_hit_sound: &Handle
     }
 }
 
-fn select_powerup(world: &mut World) -> Option<Powerup> {
-    let in_world: Vec<_> = world
-        .query::<&Powerup>()
-        .iter()
-        .map(|i| i.1.effect.clone())
-        .collect();
+/// Sounds, glows and popups for the pegs hit since the last call.
+fn run_peg_hit_effects(world: &mut World, resources: &mut Resources, audio: &Audio) {
+    let peg_hits: Vec<PegHit> = resources.get::<LevelState>().peg_hits.drain(..).collect();
+
+    let mut level_state = resources.get::<LevelState>();
+
+    for PegHit {
+        entity,
+        peg_type,
+        position,
+        combo,
+        impulse,
+    } in peg_hits
+    {
+        audio.play_peg_hit(resources, peg_type, combo, impulse);
+
+        if let Ok(visuals) = world.get::<&PegVisuals>(entity) {
+            if let Ok(mut material) = world.get::<&mut Handle<Material>>(entity) {
+                *material = visuals.glowing_material.clone();
+            }
+            if let Ok(mut scale) = world.get::<&mut Scale>(visuals.shockwave_child) {
+                scale.t = 0.5;
+            }
+        }
+
+        match peg_type {
+            PegType::Multiplier => {
+                world.spawn((DelayedAction::new(
+                    move |world, resources| {
+                        world.spawn((
+                            Transform::new()
+                                .with_position(position - Vec2::fill(2.0).extend(0.0))
+                                .with_scale(Vec3::fill(20.0)),
+                            resources.get::<GameAssets>().x2.clone(),
+                            Mesh::VERTICAL_QUAD,
+                            Temporary(90),
+                        ));
+                    },
+                    0.01,
+                ),));
+            }
+            _ => {}
+        }
+        match peg_type {
+            PegType::Gold => {
+                world.spawn((DelayedAction::new(
+                    move |world, resources| {
+                        world.spawn((
+                            Transform::new()
+                                .with_position(position + Vec2::fill(2.0).extend(0.0))
+                                .with_scale(Vec3::fill(15.0)),
+                            resources.get::<GameAssets>().plus_twenty.clone(),
+                            Mesh::VERTICAL_QUAD,
+                            Temporary(50),
+                        ));
+                    },
+                    0.01,
+                ),));
+
+                level_state.screen_shake_amount += 0.3;
+            }
+            PegType::Plant => {
+                world.spawn((DelayedAction::new(
+                    move |world, resources| {
+                        world.spawn((
+                            Transform::new()
+                                .with_position(position + Vec2::fill(2.0).extend(0.0))
+                                .with_scale(Vec3::fill(12.0)),
+                            resources.get::<GameAssets>().plus_one.clone(),
+                            Mesh::VERTICAL_QUAD,
+                            Temporary(30),
+                        ));
+                    },
+                    0.01,
+                ),));
+
+                level_state.screen_shake_amount += 0.05;
+            }
+            PegType::MultiBall => {
+                level_state.screen_shake_amount += 0.1;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn select_powerup(world: &mut World, resources: &Resources) -> Option<Powerup> {
+    let in_world: Vec<_> = world.query::<&Powerup>().iter().map(|i| i.1.id).collect();
 
     println!("IN WORLD: {:?}", in_world);
-    let mut random = Random::new();
+    let registry = resources.get::<PowerupRegistry>();
+    let ids = registry.ids();
+    if ids.is_empty() {
+        return None;
+    }
+    let mut random = resources.get::<GameRandom>();
 
-    let mut replacement_type = random.select_from_slice(&POWERUPS).clone();
+    let mut replacement_type = *random.select_from_slice(&ids);
 
     let mut max_iterations = 10;
-    while in_world.contains(&replacement_type.effect) {
-        replacement_type = random.select_from_slice(&POWERUPS).clone();
+    while in_world.contains(&replacement_type) {
+        replacement_type = *random.select_from_slice(&ids);
         if max_iterations == 0 {
             return None;
         }
         max_iterations -= 1;
     }
-    Some(replacement_type)
+    Some(Powerup {
+        id: replacement_type,
+        cost: registry.get(replacement_type).cost(),
+    })
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -1570,14 +1465,58 @@ enum PegType {
     MultiBall,
     Multiplier,
 }
+
+const PEG_TYPES: [PegType; 6] = [
+    PegType::GrowablePlant,
+    PegType::Plant,
+    PegType::Gold,
+    PegType::Stone,
+    PegType::MultiBall,
+    PegType::Multiplier,
+];
+
+impl PegType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "GrowablePlant" => PegType::GrowablePlant,
+            "Plant" => PegType::Plant,
+            "Gold" => PegType::Gold,
+            "Stone" => PegType::Stone,
+            "MultiBall" => PegType::MultiBall,
+            "Multiplier" => PegType::Multiplier,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PegType::GrowablePlant => "GrowablePlant",
+            PegType::Plant => "Plant",
+            PegType::Gold => "Gold",
+            PegType::Stone => "Stone",
+            PegType::MultiBall => "MultiBall",
+            PegType::Multiplier => "Multiplier",
+        }
+    }
+
+    /// Shown when the mouse is over a peg of this type.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PegType::GrowablePlant => "A seed. Grows into a plant when collected.",
+            PegType::Plant => "A plant. Worth 1 gold.",
+            PegType::Gold => "Gold! Worth 20 gold.",
+            PegType::Stone => "A stone. Worth nothing.",
+            PegType::MultiBall => "Turns into another ball.",
+            PegType::Multiplier => "Doubles the gold multiplier.",
+        }
+    }
+}
 fn spawn_peg(
     world: &mut World,
     resources: &Resources,
     position: Vec2,
     peg_type: PegType,
 ) -> Entity {
-    let game_assets = resources.get::<GameAssets>();
-
     let mut scale = 4.0 * 2.4;
 
     if peg_type == PegType::Gold {
@@ -1589,67 +1528,134 @@ fn spawn_peg(
         let rapier_integration = q.iter().next().unwrap().1;
         rapier_integration.add_rigid_body_with_collider(
             RigidBodyBuilder::kinematic_position_based().build(),
-            ColliderBuilder::ball(scale * 0.3).restitution(0.7).build(),
+            ColliderBuilder::ball(scale * 0.3)
+                .restitution(resources.get::<Tuning>().restitution)
+                .build(),
         )
     };
 
-    let PegMaterial {
-        base,
-        glowing,
-        shockwave,
-    } = match peg_type {
-        PegType::GrowablePlant => &game_assets.growable_plant_material,
-        PegType::Plant => &game_assets.plant_material,
-        PegType::Gold => &game_assets.gold_material,
-        PegType::Stone => &game_assets.stone_material,
-        PegType::MultiBall => &game_assets.multiball_material,
-        PegType::Multiplier => &game_assets.multiplier_material,
-    };
-
-    let child = world.spawn((
-        Transform::new().with_position(Vec3::Z * -0.01),
-        shockwave.clone(),
-        Mesh::VERTICAL_QUAD,
-        Scale {
-            rate: 5.0,
-            t: 1.0,
-            max_scale: 1.5,
-            t_max: 2.0,
-        },
-    ));
-
     let position = position.extend(0.3);
 
     let parent = world.spawn((
         Transform::new()
             .with_scale(Vec3::fill(scale))
             .with_position(position),
-        Mesh::VERTICAL_QUAD,
         Peg {
             hit: false,
-            glowing_material: glowing.clone(),
-            shockwave_child: child,
             peg_type: peg_type.clone(),
         },
         Health(1.0),
-        base.clone(),
         rapier_handle,
     ));
     match peg_type {
         PegType::GrowablePlant => {
-            let _ = world.insert(parent, (Plant { last_direction: 0 },));
+            let _ = world.insert(parent, (Plant { species: None },));
         }
         _ => {}
     }
-    let _ = world.set_parent(parent, child);
+
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        let PegMaterial {
+            base,
+            glowing,
+            shockwave,
+        } = game_assets.peg_material(peg_type);
+
+        let child = world.spawn((
+            Transform::new().with_position(Vec3::Z * -0.01),
+            shockwave.clone(),
+            Mesh::VERTICAL_QUAD,
+            Scale {
+                rate: 5.0,
+                t: 1.0,
+                max_scale: 1.5,
+                t_max: 2.0,
+            },
+        ));
+
+        let _ = world.insert(
+            parent,
+            (
+                Mesh::VERTICAL_QUAD,
+                base.clone(),
+                PegVisuals {
+                    glowing_material: glowing.clone(),
+                    shockwave_child: child,
+                },
+            ),
+        );
+        let _ = world.set_parent(parent, child);
+        spawn_peg_glyph(world, parent, peg_type, &game_assets.glyph_material);
+    }
     parent
 }
 
+/// A peg swinging from `pivot` on a joint, pushed around by gravity and balls.
+struct Pendulum {
+    pivot: Vec2,
+}
+
+/// The fixed point a `Pendulum` hangs from.
+/// Stays on the board after its peg is collected, like a brick.
+struct PendulumPivot;
+
+const PENDULUM_PIVOT_SIZE: f32 = 2.0;
+
+fn spawn_pendulum(
+    world: &mut World,
+    resources: &Resources,
+    peg_type: PegType,
+    pivot: Vec2,
+    position: Vec2,
+) -> Entity {
+    let peg = spawn_peg(world, resources, position, peg_type);
+    let _ = world.insert_one(peg, Pendulum { pivot });
+
+    // The pivot needs a collider so `update_colliders` removes its body once it's despawned.
+    // A sensor keeps balls from bouncing off it.
+    let rapier_handle = {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.add_rigid_body_with_collider(
+            RigidBodyBuilder::fixed().build(),
+            ColliderBuilder::ball(PENDULUM_PIVOT_SIZE / 2.0)
+                .sensor(true)
+                .build(),
+        )
+    };
+    let pivot_entity = world.spawn((
+        Transform::new()
+            .with_position(pivot.extend(0.3))
+            .with_scale(Vec3::fill(PENDULUM_PIVOT_SIZE)),
+        PendulumPivot,
+        rapier_handle,
+    ));
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        let _ = world.insert(
+            pivot_entity,
+            (Mesh::VERTICAL_QUAD, game_assets.brick_material.clone()),
+        );
+    }
+
+    {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.set_body_type(world, peg, RigidBodyType::Dynamic);
+        rapier_integration.add_joint(
+            world,
+            pivot_entity,
+            peg,
+            revolute_joint(Vec2::ZERO, pivot - position),
+        );
+    }
+    peg
+}
+
+/// A shop brick. Each hit lowers `cost` and the powerup is bought when it reaches zero.
 #[derive(Clone)]
 struct Powerup {
+    id: PowerupId,
     cost: i32,
-    description: &'static str,
-    effect: Effects,
 }
 
 fn spawn_brick(
@@ -1658,15 +1664,13 @@ fn spawn_brick(
     position: Vec2,
     dimensions: Vec2,
 ) -> Entity {
-    let game_assets = resources.get::<GameAssets>();
-
     let rapier_handle = {
         let mut q = world.query::<&mut RapierIntegration>();
         let rapier_integration = q.iter().next().unwrap().1;
         rapier_integration.add_rigid_body_with_collider(
             RigidBodyBuilder::kinematic_position_based().build(),
             ColliderBuilder::cuboid(dimensions.x / 2.0 * 0.98, dimensions.y / 2.0 * 0.98)
-                .restitution(0.7)
+                .restitution(resources.get::<Tuning>().restitution)
                 .build(),
         )
     };
@@ -1677,15 +1681,237 @@ fn spawn_brick(
         Transform::new()
             .with_scale(dimensions.extend(1.0))
             .with_position(position),
-        Mesh::VERTICAL_QUAD,
         Health(1.0),
-        game_assets.brick_material.clone(),
+        Brick,
+        rapier_handle,
+    ));
+
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        let _ = world.insert(
+            parent,
+            (Mesh::VERTICAL_QUAD, game_assets.brick_material.clone()),
+        );
+    }
+    parent
+}
+
+const RAIL_THICKNESS: f32 = 2.0;
+
+/// One straight piece of a rail, from `a` to `b`.
+/// Rails are part of the board, so unlike bricks they aren't worn down by stuck balls.
+struct Rail {
+    a: Vec2,
+    b: Vec2,
+}
+
+fn spawn_rail(world: &mut World, resources: &Resources, a: Vec2, b: Vec2) -> Entity {
+    let length = (b - a).length();
+    let rapier_handle = {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.add_rigid_body_with_collider(
+            RigidBodyBuilder::kinematic_position_based().build(),
+            capsule_collider(
+                Vec2::new(-length / 2.0, 0.0),
+                Vec2::new(length / 2.0, 0.0),
+                RAIL_THICKNESS / 2.0,
+            )
+            .restitution(resources.get::<Tuning>().restitution)
+            .build(),
+        )
+    };
+
+    let direction = b - a;
+    let angle = direction.y.atan2(direction.x);
+
+    let rail = world.spawn((
+        Transform::new()
+            .with_position(((a + b) / 2.0).extend(0.3))
+            .with_rotation(Quat::from_angle_axis(angle, Vec3::Z))
+            .with_scale(Vec3::new(length, RAIL_THICKNESS, 1.0)),
+        Rail { a, b },
+        rapier_handle,
+    ));
+
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        let _ = world.insert(
+            rail,
+            (Mesh::VERTICAL_QUAD, game_assets.brick_material.clone()),
+        );
+    }
+    rail
+}
+
+/// An angled bumper shaped like the convex hull of `points`, which are offsets from its position.
+/// Like rails, these aren't worn down by stuck balls.
+struct Bumper {
+    points: Vec<Vec2>,
+}
+
+/// The art for one edge of a `Bumper`.
+struct BumperEdge;
+
+fn spawn_bumper(
+    world: &mut World,
+    resources: &Resources,
+    position: Vec2,
+    points: Vec<Vec2>,
+) -> Entity {
+    let collider = convex_hull_collider(&points)
+        .unwrap()
+        .restitution(resources.get::<Tuning>().restitution)
+        .build();
+    // The hull's corners in order, leaving out any points inside it.
+    let corners: Vec<Vec2> = collider
+        .shape()
+        .as_convex_polygon()
+        .unwrap()
+        .points()
+        .iter()
+        .map(|p| Vec2::new(p.x, p.y))
+        .collect();
+    let rapier_handle = {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.add_rigid_body_with_collider(
+            RigidBodyBuilder::kinematic_position_based().build(),
+            collider,
+        )
+    };
+
+    let parent = world.spawn((
+        Transform::new().with_position(position.extend(0.3)),
+        Bumper { points },
+        rapier_handle,
+    ));
+
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        for (i, a) in corners.iter().enumerate() {
+            let b = corners[(i + 1) % corners.len()];
+            let direction = b - *a;
+            let edge = world.spawn((
+                Transform::new()
+                    .with_position(((*a + b) / 2.0).extend(0.0))
+                    .with_rotation(Quat::from_angle_axis(
+                        direction.y.atan2(direction.x),
+                        Vec3::Z,
+                    ))
+                    .with_scale(Vec3::new(direction.length(), RAIL_THICKNESS, 1.0)),
+                Mesh::VERTICAL_QUAD,
+                game_assets.brick_material.clone(),
+                BumperEdge,
+            ));
+            let _ = world.set_parent(parent, edge);
+        }
+    }
+    parent
+}
+
+/// Bricks joined into one body. Parts are an offset from the brick's position and dimensions.
+/// Like rails, these aren't worn down by stuck balls.
+struct CompoundBrick {
+    parts: Vec<(Vec2, Vec2)>,
+}
+
+/// The art for one part of a `CompoundBrick`.
+struct CompoundBrickPart;
+
+fn spawn_compound_brick(
+    world: &mut World,
+    resources: &Resources,
+    position: Vec2,
+    parts: Vec<(Vec2, Vec2)>,
+) -> Entity {
+    let restitution = resources.get::<Tuning>().restitution;
+    let colliders = parts
+        .iter()
+        .map(|(offset, dimensions)| {
+            ColliderBuilder::cuboid(dimensions.x / 2.0 * 0.98, dimensions.y / 2.0 * 0.98)
+                .translation([offset.x, offset.y].into())
+                .restitution(restitution)
+                .build()
+        })
+        .collect();
+    let rapier_handle = {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.add_rigid_body_with_colliders(
+            RigidBodyBuilder::kinematic_position_based().build(),
+            colliders,
+        )
+    };
+
+    let parent = world.spawn((
+        Transform::new().with_position(position.extend(0.3)),
         rapier_handle,
     ));
 
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        for (offset, dimensions) in &parts {
+            let part = world.spawn((
+                Transform::new()
+                    .with_position(offset.extend(0.0))
+                    .with_scale(dimensions.extend(1.0)),
+                Mesh::VERTICAL_QUAD,
+                game_assets.brick_material.clone(),
+                CompoundBrickPart,
+            ));
+            let _ = world.set_parent(parent, part);
+        }
+    }
+    let _ = world.insert_one(parent, CompoundBrick { parts });
     parent
 }
 
+/// A bucket sliding along the bottom of the board.
+/// Catching a ball refunds the shot, see `run_catchers`.
+struct Catcher;
+
+fn spawn_catcher(world: &mut World, resources: &mut Resources, width: f32, period: f32) -> Entity {
+    let dimensions = Vec2::new(width, 4.0);
+    let y = -VIEW_HEIGHT / 2.0 + dimensions.y;
+    // Slides until it touches either side of the board.
+    let x = VIEW_WIDTH / 2.0 - width / 2.0;
+    let mover = PathMover {
+        path: Path::PingPong {
+            from: Vec2::new(-x, y),
+            to: Vec2::new(x, y),
+        },
+        period,
+        t: 0.0,
+    };
+
+    let rapier_handle = {
+        let mut q = world.query::<&mut RapierIntegration>();
+        let rapier_integration = q.iter().next().unwrap().1;
+        rapier_integration.add_rigid_body_with_collider(
+            RigidBodyBuilder::kinematic_position_based().build(),
+            ColliderBuilder::cuboid(dimensions.x / 2.0, dimensions.y / 2.0)
+                .sensor(true)
+                .build(),
+        )
+    };
+
+    let position = mover.path.position(mover.t).extend(0.3);
+
+    let catcher = world.spawn((
+        Transform::new()
+            .with_scale(dimensions.extend(1.0))
+            .with_position(position),
+        Catcher,
+        mover,
+        rapier_handle,
+    ));
+
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        let _ = world.insert(
+            catcher,
+            (Mesh::VERTICAL_QUAD, game_assets.brick_material.clone()),
+        );
+    }
+    catcher
+}
+
 fn spawn_brick_with_powerup(
     world: &mut World,
     resources: &mut Resources,
@@ -1694,18 +1920,11 @@ fn spawn_brick_with_powerup(
 ) -> Entity {
     let dimensions = Vec2::new(40.0, 20.0);
 
-    let name = powerup.description;
-
-    let screen_space_ui = ScreenSpaceUI::new(
-        world,
-        resources,
-        kui::center(kui::text(move |state: &mut UIState| {
-            let mut name = name.to_string().clone();
-            name.push_str(&format!(": {:?}", state.hacky_remaining_health));
-            name
-        })),
-    );
-    let game_assets = resources.get::<GameAssets>();
+    let name = resources
+        .get::<PowerupRegistry>()
+        .get(powerup.id)
+        .description()
+        .to_string();
 
     let rapier_handle = {
         let mut q = world.query::<&mut RapierIntegration>();
@@ -1713,7 +1932,7 @@ fn spawn_brick_with_powerup(
         rapier_integration.add_rigid_body_with_collider(
             RigidBodyBuilder::kinematic_position_based().build(),
             ColliderBuilder::cuboid(dimensions.x / 2.0 * 0.98, dimensions.y / 2.0 * 0.98)
-                .restitution(0.7)
+                .restitution(resources.get::<Tuning>().restitution)
                 .build(),
         )
     };
@@ -1724,42 +1943,63 @@ fn spawn_brick_with_powerup(
         Transform::new()
             .with_scale(dimensions.extend(1.0))
             .with_position(position),
-        Mesh::VERTICAL_QUAD,
         powerup,
-        game_assets.brick_material.clone(),
         rapier_handle,
     ));
 
-    let _ = world.set_parent(parent, screen_space_ui);
+    if resources.try_get::<GameAssets>().is_some() {
+        let screen_space_ui = ScreenSpaceUI::new(
+            world,
+            resources,
+            kui::center(kui::text(move |state: &mut UIState| {
+                format!("{}: {:?}", name, state.hacky_remaining_health)
+            })),
+        );
+        let game_assets = resources.get::<GameAssets>();
+        let _ = world.insert(
+            parent,
+            (Mesh::VERTICAL_QUAD, game_assets.brick_material.clone()),
+        );
+        let _ = world.set_parent(parent, screen_space_ui);
+    }
     parent
 }
 
+/// Plain bricks spawned with `spawn_brick`, as opposed to shop bricks holding a `Powerup`.
+struct Brick;
+
+/// Kept on plant stems so they can be saved.
+struct Stem {
+    position: Vec2,
+    direction: Vec2,
+}
+
+fn spawn_stem(world: &mut World, resources: &Resources, position: Vec2, stem_direction: Vec2) {
+    if let Some(game_assets) = resources.try_get::<GameAssets>() {
+        world.spawn((
+            Stem {
+                position,
+                direction: stem_direction,
+            },
+            Mesh::VERTICAL_QUAD,
+            Transform::new()
+                .with_position((position - stem_direction / 2.0).extend(0.2))
+                .with_rotation(Quat::from_forward_up(
+                    -Vec3::Z,
+                    stem_direction.normalized().extend(0.0),
+                ))
+                .with_scale(Vec3::new(2.0, stem_direction.length() * 1.1, 1.0)),
+            game_assets.stem_material.clone(),
+        ));
+    }
+}
+
 fn spawn_gold(world: &mut World, resources: &Resources, position: Vec2, stem_direction: Vec2) {
-    world.spawn((
-        Mesh::VERTICAL_QUAD,
-        Transform::new()
-            .with_position((position - stem_direction / 2.0).extend(0.2))
-            .with_rotation(Quat::from_forward_up(
-                -Vec3::Z,
-                stem_direction.normalized().extend(0.0),
-            ))
-            .with_scale(Vec3::new(2.0, stem_direction.length() * 1.1, 1.0)),
-        resources.get::<GameAssets>().stem_material.clone(),
-    ));
+    spawn_stem(world, resources, position, stem_direction);
     spawn_peg(world, resources, position, PegType::Gold);
 }
 
 fn spawn_plant(world: &mut World, resources: &Resources, position: Vec2, stem_direction: Vec2) {
     spawn_peg(world, resources, position, PegType::Plant);
-    world.spawn((
-        Mesh::VERTICAL_QUAD,
-        Transform::new()
-            .with_position((position - stem_direction / 2.0).extend(0.2))
-            .with_rotation(Quat::from_forward_up(
-                -Vec3::Z,
-                stem_direction.normalized().extend(0.0),
-            ))
-            .with_scale(Vec3::new(2.0, stem_direction.length() * 1.1, 1.0)),
-        resources.get::<GameAssets>().stem_material.clone(),
-    ));
+    spawn_stem(world, resources, position, stem_direction);
 }
@@ -0,0 +1,226 @@
+use crate::*;
+
+pub const MANIFEST_PATH: &str = "assets/assets.txt";
+const DEFAULT_MANIFEST: &str = include_str!("../assets/assets.txt");
+
+pub struct MaterialEntry {
+    pub shader: String,
+    pub texture: Option<String>,
+    pub color: Color,
+}
+
+/// Every named asset in `assets/assets.txt`.
+pub struct AssetManifest {
+    shaders: Vec<(String, String)>,
+    materials: Vec<(String, MaterialEntry)>,
+    sounds: Vec<(String, String)>,
+    /// Shaders already loaded by `update_material`, so each is only loaded once.
+    loaded_shaders: Vec<(String, Handle<Shader>)>,
+}
+
+impl AssetManifest {
+    pub fn load() -> Self {
+        let source = read_data_file(MANIFEST_PATH, DEFAULT_MANIFEST);
+        match Self::parse(&source) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Could not load {:?}: {}", MANIFEST_PATH, e);
+                Self::parse(DEFAULT_MANIFEST).unwrap()
+            }
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut manifest = Self {
+            shaders: Vec::new(),
+            materials: Vec::new(),
+            sounds: Vec::new(),
+            loaded_shaders: Vec::new(),
+        };
+
+        for (line, tokens) in data_lines(source) {
+            let name: String = parse_token(&tokens, 1, line)?;
+            match tokens[0] {
+                "shader" => manifest
+                    .shaders
+                    .push((name, parse_token(&tokens, 2, line)?)),
+                "material" => {
+                    let texture: String = parse_token(&tokens, 3, line)?;
+                    manifest.materials.push((
+                        name,
+                        MaterialEntry {
+                            shader: parse_token(&tokens, 2, line)?,
+                            texture: if texture == "none" {
+                                None
+                            } else {
+                                Some(texture)
+                            },
+                            color: parse_color(&tokens, 4, line)?,
+                        },
+                    ))
+                }
+                "sound" => manifest.sounds.push((name, parse_token(&tokens, 2, line)?)),
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn material(&self, name: &str) -> Option<&MaterialEntry> {
+        self.materials
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, m)| m)
+    }
+
+    pub fn sound_path(&self, name: &str) -> Option<&str> {
+        self.sounds
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, path)| path.as_str())
+    }
+
+    fn load_shader(&mut self, resources: &Resources, name: &str) -> Handle<Shader> {
+        match name {
+            "unlit" => return Shader::UNLIT,
+            "unlit_transparent" => return Shader::UNLIT_TRANSPARENT,
+            _ => {}
+        }
+        if let Some((_, shader)) = self.loaded_shaders.iter().find(|(n, _)| n == name) {
+            return shader.clone();
+        }
+
+        let shader = match self.shaders.iter().find(|(n, _)| n == name) {
+            Some((_, path)) => resources.get::<AssetStore<Shader>>().load(
+                path,
+                ShaderSettings {
+                    blending: Some((BlendFactor::One, BlendFactor::OneMinusSourceAlpha)),
+                    ..Default::default()
+                },
+            ),
+            None => {
+                println!("No shader named {:?} in {:?}", name, MANIFEST_PATH);
+                Shader::UNLIT
+            }
+        };
+        self.loaded_shaders.push((name.to_string(), shader.clone()));
+        shader
+    }
+
+    /// Replaces the material behind `handle` with the entry called `name`,
+    /// so everything using the handle changes too.
+    /// A missing entry is reported and the material is left alone.
+    pub fn update_material(
+        &mut self,
+        resources: &Resources,
+        name: &str,
+        handle: &Handle<Material>,
+    ) {
+        let (shader, texture, color) = match self.material(name) {
+            Some(entry) => (entry.shader.clone(), entry.texture.clone(), entry.color),
+            None => {
+                println!("No material named {:?} in {:?}", name, MANIFEST_PATH);
+                return;
+            }
+        };
+
+        let texture = texture.map(|path| {
+            resources
+                .get::<AssetStore<Texture>>()
+                .load(&path, koi_graphics_context::TextureSettings::default())
+        });
+        let shader = self.load_shader(resources, &shader);
+        *resources.get::<AssetStore<Material>>().get_mut(handle) = Material {
+            shader,
+            base_color_texture: texture,
+            base_color: color,
+            ..Default::default()
+        };
+    }
+}
+
+/// The part of a peg's asset names after `peg.`, like `multi_ball` in `peg.multi_ball.base`.
+pub fn peg_asset_name(peg_type: PegType) -> &'static str {
+    match peg_type {
+        PegType::GrowablePlant => "growable_plant",
+        PegType::Plant => "plant",
+        PegType::Gold => "gold",
+        PegType::Stone => "stone",
+        PegType::MultiBall => "multi_ball",
+        PegType::Multiplier => "multiplier",
+    }
+}
+
+/// Parses a color and its modifiers from `tokens[index..]`. See `assets/assets.txt`.
+fn parse_color(tokens: &[&str], index: usize, line: usize) -> Result<Color, String> {
+    let name: String = parse_token(tokens, index, line)?;
+    let mut color = match name.as_str() {
+        "white" => Color::WHITE,
+        "black" => Color::BLACK,
+        "orange" => Color::ORANGE,
+        "green" => Color::GREEN,
+        "yellow" => Color::YELLOW,
+        "purple" => Color::PURPLE,
+        "electric_indigo" => Color::ELECTRIC_INDIGO,
+        "transparent" => Color::TRANSPARENT,
+        hex => match u32::from_str_radix(hex, 16) {
+            Ok(hex) => Color::from_srgb_hex(hex, 1.0),
+            Err(_) => return Err(format!("Line {}: unknown color {:?}", line, hex)),
+        },
+    };
+
+    let mut i = index + 1;
+    while i < tokens.len() {
+        let value: f32 = parse_token(tokens, i + 1, line)?;
+        color = match tokens[i] {
+            "lightness" => color.with_lightness(value),
+            "chroma" => color.with_chroma(value),
+            other => return Err(format!("Line {}: unknown color modifier {:?}", line, other)),
+        };
+        i += 2;
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_manifest_parses() {
+        let manifest = AssetManifest::parse(DEFAULT_MANIFEST).unwrap();
+        for peg_type in PEG_TYPES {
+            let name = format!("peg.{}", peg_asset_name(peg_type));
+            assert!(manifest.sound_path(&name).is_some(), "{} is missing", name);
+            for part in ["base", "glowing", "shockwave"] {
+                let material = format!("{}.{}", name, part);
+                assert!(
+                    manifest.material(&material).is_some(),
+                    "{} is missing",
+                    material
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn materials() {
+        let manifest = AssetManifest::parse(
+            "material a unlit none white\nmaterial b recolor assets/Peg.png 489B41 lightness 0.5",
+        )
+        .unwrap();
+        assert!(manifest.material("a").unwrap().texture.is_none());
+        let b = manifest.material("b").unwrap();
+        assert_eq!(b.shader, "recolor");
+        assert_eq!(b.texture.as_deref(), Some("assets/Peg.png"));
+        assert!(manifest.material("c").is_none());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(AssetManifest::parse("texture a b").is_err());
+        assert!(AssetManifest::parse("material a unlit none mauve").is_err());
+        assert!(AssetManifest::parse("sound a").is_err());
+    }
+}
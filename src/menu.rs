@@ -0,0 +1,277 @@
+use crate::*;
+
+/// Which screen is showing. `Playing`, `Shop` and `Summary` follow the `LevelState`,
+/// the others are menus that pause the game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Screen {
+    MainMenu,
+    Playing,
+    Paused,
+    Settings,
+    Shop,
+    Summary,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuItem {
+    Play,
+    Resume,
+    NewRun,
+    Settings,
+    ScreenShake,
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    AimPreview,
+    Palette,
+    PegGlyphs,
+    UiScale,
+    Back,
+}
+
+impl MenuItem {
+    fn label(self, settings: &Settings) -> String {
+        match self {
+            MenuItem::Play => "Play".into(),
+            MenuItem::Resume => "Resume".into(),
+            MenuItem::NewRun => "New run".into(),
+            MenuItem::Settings => "Settings".into(),
+            MenuItem::ScreenShake => format!("Screen shake: {}", percent(settings.screen_shake)),
+            MenuItem::MasterVolume => format!("Volume: {}", percent(settings.master_volume)),
+            MenuItem::SfxVolume => format!("Effects volume: {}", percent(settings.sfx_volume)),
+            MenuItem::MusicVolume => format!("Music volume: {}", percent(settings.music_volume)),
+            MenuItem::AimPreview => format!("Aim preview: {}", settings.aim_preview.name()),
+            MenuItem::Palette => format!("Colors: {}", settings.palette.label()),
+            MenuItem::PegGlyphs => format!(
+                "Peg symbols: {}",
+                if settings.peg_glyphs { "On" } else { "Off" }
+            ),
+            MenuItem::UiScale => match settings.ui_scale {
+                Some(scale) => format!("Interface size: x{}", scale),
+                None => "Interface size: Auto".into(),
+            },
+            MenuItem::Back => "Back".into(),
+        }
+    }
+
+    /// Left and right arrows change settings. Other items ignore them.
+    fn adjust(self, settings: &mut Settings, direction: f32) {
+        match self {
+            MenuItem::ScreenShake => step(&mut settings.screen_shake, direction * 0.25),
+            MenuItem::MasterVolume => step(&mut settings.master_volume, direction * 0.1),
+            MenuItem::SfxVolume => step(&mut settings.sfx_volume, direction * 0.1),
+            MenuItem::MusicVolume => step(&mut settings.music_volume, direction * 0.1),
+            MenuItem::AimPreview => {
+                settings.aim_preview = if direction > 0.0 {
+                    settings.aim_preview.next()
+                } else {
+                    settings.aim_preview.previous()
+                }
+            }
+            MenuItem::Palette => {
+                settings.palette = if direction > 0.0 {
+                    settings.palette.next()
+                } else {
+                    settings.palette.previous()
+                }
+            }
+            MenuItem::PegGlyphs => settings.peg_glyphs = !settings.peg_glyphs,
+            MenuItem::UiScale => {
+                let i = UI_SCALES
+                    .iter()
+                    .position(|s| *s == settings.ui_scale)
+                    .unwrap_or(0);
+                let i = if direction > 0.0 {
+                    (i + 1) % UI_SCALES.len()
+                } else {
+                    (i + UI_SCALES.len() - 1) % UI_SCALES.len()
+                };
+                settings.ui_scale = UI_SCALES[i];
+            }
+            _ => {}
+        }
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{}%", (value * 100.0).round())
+}
+
+/// Moves a 0 to 1 setting by `amount`, snapped to the step so it doesn't drift.
+fn step(value: &mut f32, amount: f32) {
+    let steps = (*value / amount.abs() + amount.signum()).round();
+    *value = (steps * amount.abs()).clamp(0.0, 1.0);
+}
+
+pub struct MenuState {
+    pub screen: Screen,
+    /// The screen to return to when leaving the settings.
+    settings_parent: Screen,
+    selected: usize,
+}
+
+impl MenuState {
+    pub fn new(screen: Screen) -> Self {
+        Self {
+            screen,
+            settings_parent: Screen::MainMenu,
+            selected: 0,
+        }
+    }
+
+    /// `FixedUpdate` doesn't run while a menu is open.
+    pub fn is_paused(&self) -> bool {
+        matches!(
+            self.screen,
+            Screen::MainMenu | Screen::Paused | Screen::Settings
+        )
+    }
+
+    fn items(&self) -> &'static [MenuItem] {
+        match self.screen {
+            Screen::MainMenu => &[MenuItem::Play, MenuItem::Settings],
+            Screen::Paused => &[MenuItem::Resume, MenuItem::NewRun, MenuItem::Settings],
+            Screen::Settings => &[
+                MenuItem::ScreenShake,
+                MenuItem::MasterVolume,
+                MenuItem::SfxVolume,
+                MenuItem::MusicVolume,
+                MenuItem::AimPreview,
+                MenuItem::Palette,
+                MenuItem::PegGlyphs,
+                MenuItem::UiScale,
+                MenuItem::Back,
+            ],
+            _ => &[],
+        }
+    }
+
+    fn open(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.selected = 0;
+    }
+
+    /// The open menu with its selected item marked, or `None` outside of menus.
+    pub fn text(&self, settings: &Settings) -> Option<String> {
+        let title = match self.screen {
+            Screen::MainMenu => "Welcome to my farm!",
+            Screen::Paused => "Paused",
+            Screen::Settings => "Settings",
+            _ => return None,
+        };
+
+        let mut lines = vec![title.to_string(), String::new()];
+        for (i, item) in self.items().iter().enumerate() {
+            let label = item.label(settings);
+            if i == self.selected {
+                lines.push(format!("> {} <", label));
+            } else {
+                lines.push(label);
+            }
+        }
+        lines.push(String::new());
+        lines.push("Arrow keys to choose, Space to select".to_string());
+        Some(lines.join("\n"))
+    }
+}
+
+/// Keeps the screen in step with the `LevelState` while no menu is open.
+pub fn update_screen(resources: &Resources) {
+    let mut menu_state = resources.get::<MenuState>();
+    if menu_state.is_paused() {
+        return;
+    }
+    let level_state = resources.get::<LevelState>();
+    menu_state.screen = if level_state.round_over || level_state.game_over {
+        Screen::Summary
+    } else if level_state.in_shop {
+        Screen::Shop
+    } else {
+        Screen::Playing
+    };
+}
+
+/// Returns false if the key wasn't used by a menu and should go to the game.
+/// Escape opens the pause menu from any other screen.
+pub fn menu_key_down(world: &mut World, resources: &mut Resources, key: Key) -> bool {
+    let selected = {
+        let mut menu_state = resources.get::<MenuState>();
+        let mut settings = resources.get::<Settings>();
+
+        if !menu_state.is_paused() {
+            if key != Key::Escape {
+                return false;
+            }
+            menu_state.open(Screen::Paused);
+            return true;
+        }
+
+        let items = menu_state.items();
+        let item = items[menu_state.selected];
+        match key {
+            Key::Escape => {
+                match menu_state.screen {
+                    Screen::Paused => menu_state.screen = Screen::Playing,
+                    Screen::Settings => {
+                        let parent = menu_state.settings_parent;
+                        menu_state.open(parent);
+                        settings.save();
+                    }
+                    _ => {}
+                }
+                None
+            }
+            Key::Up => {
+                menu_state.selected = (menu_state.selected + items.len() - 1) % items.len();
+                None
+            }
+            Key::Down => {
+                menu_state.selected = (menu_state.selected + 1) % items.len();
+                None
+            }
+            Key::Left => {
+                item.adjust(&mut settings, -1.0);
+                None
+            }
+            Key::Right => {
+                item.adjust(&mut settings, 1.0);
+                None
+            }
+            Key::Space => Some(item),
+            _ => None,
+        }
+    };
+
+    if let Some(item) = selected {
+        select(world, resources, item);
+    }
+    resources.get::<GameAssets>().apply_settings(resources);
+    update_screen(resources);
+    true
+}
+
+fn select(world: &mut World, resources: &mut Resources, item: MenuItem) {
+    match item {
+        MenuItem::Play | MenuItem::Resume => resources.get::<MenuState>().screen = Screen::Playing,
+        MenuItem::NewRun => {
+            if can_restart(resources) {
+                apply_replay_event(world, resources, ReplayEvent::Restart);
+                resources.get::<MenuState>().screen = Screen::Playing;
+            } else {
+                resources.get::<UIState>().current_text = "Wait for the ball to land first!".into();
+            }
+        }
+        MenuItem::Settings => {
+            let mut menu_state = resources.get::<MenuState>();
+            menu_state.settings_parent = menu_state.screen;
+            menu_state.open(Screen::Settings);
+        }
+        MenuItem::Back => {
+            let mut menu_state = resources.get::<MenuState>();
+            let parent = menu_state.settings_parent;
+            menu_state.open(parent);
+            resources.get::<Settings>().save();
+        }
+        _ => item.adjust(&mut resources.get::<Settings>(), 1.0),
+    }
+}
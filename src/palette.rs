@@ -0,0 +1,209 @@
+use crate::*;
+
+/// Colors used to tell peg types apart.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Palette {
+    Standard,
+    /// For deuteranopia and protanopia.
+    RedGreen,
+    /// For tritanopia.
+    BlueYellow,
+}
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Standard" => Palette::Standard,
+            "RedGreen" => Palette::RedGreen,
+            "BlueYellow" => Palette::BlueYellow,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::RedGreen => "RedGreen",
+            Palette::BlueYellow => "BlueYellow",
+        }
+    }
+
+    /// Shown in the settings menu.
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::RedGreen => "Red-green safe",
+            Palette::BlueYellow => "Blue-yellow safe",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Standard => Palette::RedGreen,
+            Palette::RedGreen => Palette::BlueYellow,
+            Palette::BlueYellow => Palette::Standard,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        self.next().next()
+    }
+
+    /// `None` for the Standard palette, which uses the colors in the asset manifest.
+    pub fn peg_color(self, peg_type: PegType) -> Option<Color> {
+        let stone = Color::BLACK.with_lightness(0.6);
+        Some(match self {
+            Palette::Standard => return None,
+            // Based on the Okabe-Ito colors, which stay apart with red-green color blindness.
+            Palette::RedGreen => match peg_type {
+                PegType::GrowablePlant => Color::from_srgb_hex(0xD55E00, 1.0),
+                PegType::Plant => Color::from_srgb_hex(0x56B4E9, 1.0),
+                PegType::Gold => Color::from_srgb_hex(0xF0E442, 1.0),
+                PegType::Stone => stone,
+                PegType::MultiBall => Color::from_srgb_hex(0xCC79A7, 1.0),
+                PegType::Multiplier => Color::from_srgb_hex(0x0072B2, 1.0),
+            },
+            Palette::BlueYellow => match peg_type {
+                PegType::GrowablePlant => Color::from_srgb_hex(0xD55E00, 1.0),
+                PegType::Plant => Color::from_srgb_hex(0x009E73, 1.0),
+                PegType::Gold => Color::from_srgb_hex(0xF5F5F5, 1.0),
+                PegType::Stone => stone,
+                PegType::MultiBall => Color::from_srgb_hex(0xCC79A7, 1.0),
+                PegType::Multiplier => Color::from_srgb_hex(0x882255, 1.0),
+            },
+        })
+    }
+}
+
+impl GameAssets {
+    pub fn peg_material(&self, peg_type: PegType) -> &PegMaterial {
+        match peg_type {
+            PegType::GrowablePlant => &self.growable_plant_material,
+            PegType::Plant => &self.plant_material,
+            PegType::Gold => &self.gold_material,
+            PegType::Stone => &self.stone_material,
+            PegType::MultiBall => &self.multiball_material,
+            PegType::Multiplier => &self.multiplier_material,
+        }
+    }
+
+    /// Recolors the shared peg materials, so pegs already on the board change too.
+    pub fn apply_settings(&self, resources: &Resources) {
+        let settings = resources.get::<Settings>();
+        let mut materials = resources.get::<AssetStore<Material>>();
+
+        for peg_type in PEG_TYPES {
+            let palette_color = settings.palette.peg_color(peg_type);
+            let peg_material = self.peg_material(peg_type);
+            for (material, manifest_color) in [
+                &peg_material.base,
+                &peg_material.glowing,
+                &peg_material.shockwave,
+            ]
+            .into_iter()
+            .zip(peg_material.manifest_colors)
+            {
+                materials.get_mut(material).base_color = palette_color.unwrap_or(manifest_color);
+            }
+        }
+
+        materials.get_mut(&self.glyph_material).base_color = if settings.peg_glyphs {
+            Color::from_srgb_hex(0x2B1D14, 0.9)
+        } else {
+            Color::TRANSPARENT
+        };
+    }
+}
+
+/// A shape drawn over a peg so its type doesn't rely on color alone.
+/// Glyphs are hidden by making their shared material transparent.
+pub struct PegGlyph {
+    peg: Entity,
+}
+
+pub fn spawn_peg_glyph(
+    world: &mut World,
+    peg: Entity,
+    peg_type: PegType,
+    material: &Handle<Material>,
+) {
+    let bar = Vec2::new(0.4, 0.09);
+    let quarter_turn = std::f32::consts::FRAC_PI_2;
+    let eighth_turn = std::f32::consts::FRAC_PI_4;
+
+    // (mesh, offset, size, rotation) in the peg's local space, where the peg is 1 wide.
+    let parts = match peg_type {
+        // A sprout.
+        PegType::GrowablePlant => vec![
+            (
+                Mesh::VERTICAL_QUAD,
+                Vec2::new(0.0, -0.05),
+                bar,
+                quarter_turn,
+            ),
+            (
+                Mesh::VERTICAL_CIRCLE,
+                Vec2::new(0.0, 0.15),
+                Vec2::fill(0.18),
+                0.0,
+            ),
+        ],
+        // A plus, like the +1 it gives.
+        PegType::Plant => vec![
+            (Mesh::VERTICAL_QUAD, Vec2::ZERO, bar, 0.0),
+            (Mesh::VERTICAL_QUAD, Vec2::ZERO, bar, quarter_turn),
+        ],
+        PegType::Gold => vec![(
+            Mesh::VERTICAL_QUAD,
+            Vec2::ZERO,
+            Vec2::fill(0.25),
+            eighth_turn,
+        )],
+        PegType::Stone => vec![],
+        PegType::MultiBall => vec![
+            (
+                Mesh::VERTICAL_CIRCLE,
+                Vec2::new(-0.12, 0.0),
+                Vec2::fill(0.18),
+                0.0,
+            ),
+            (
+                Mesh::VERTICAL_CIRCLE,
+                Vec2::new(0.12, 0.0),
+                Vec2::fill(0.18),
+                0.0,
+            ),
+        ],
+        // A times sign, like the x2 it gives.
+        PegType::Multiplier => vec![
+            (Mesh::VERTICAL_QUAD, Vec2::ZERO, bar, eighth_turn),
+            (Mesh::VERTICAL_QUAD, Vec2::ZERO, bar, -eighth_turn),
+        ],
+    };
+
+    for (mesh, offset, size, rotation) in parts {
+        let glyph = world.spawn((
+            Transform::new()
+                .with_position(offset.extend(0.01))
+                .with_scale(size.extend(1.0))
+                .with_rotation(Quat::from_angle_axis(rotation, Vec3::Z)),
+            mesh,
+            material.clone(),
+            PegGlyph { peg },
+        ));
+        let _ = world.set_parent(peg, glyph);
+    }
+}
+
+/// Pegs are despawned in many places, so their glyphs are cleaned up here instead.
+pub fn despawn_orphaned_glyphs(world: &mut World) {
+    let orphans: Vec<_> = world
+        .query::<&PegGlyph>()
+        .iter()
+        .filter(|(_, glyph)| !world.contains(glyph.peg))
+        .map(|(e, _)| e)
+        .collect();
+    for e in orphans {
+        let _ = world.despawn(e);
+    }
+}
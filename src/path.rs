@@ -0,0 +1,180 @@
+use crate::*;
+
+/// A route for a `PathMover`. Positions are on the board, not relative to the entity.
+#[derive(Clone, Debug)]
+pub enum Path {
+    /// Moves through each point in turn and back to the first.
+    Loop(Vec<Vec2>),
+    /// Circles `center` counter-clockwise, starting to the right of it.
+    Orbit { center: Vec2, radius: f32 },
+    /// Eases along a cubic bezier curve and back again.
+    Bezier([Vec2; 4]),
+    /// Eases from one point to the other and back again.
+    PingPong { from: Vec2, to: Vec2 },
+}
+
+impl Path {
+    /// `t` goes from 0 to 1 over one trip around the path.
+    pub fn position(&self, t: f32) -> Vec2 {
+        match self {
+            Path::Loop(points) => {
+                if points.is_empty() {
+                    return Vec2::ZERO;
+                }
+                let t = t * points.len() as f32;
+                let i = (t as usize).min(points.len() - 1);
+                let a = points[i];
+                let b = points[(i + 1) % points.len()];
+                a + (b - a) * (t - i as f32)
+            }
+            Path::Orbit { center, radius } => {
+                let angle = t * std::f32::consts::TAU;
+                *center + Vec2::new(angle.cos(), angle.sin()) * *radius
+            }
+            Path::Bezier([a, b, c, d]) => {
+                let t = ping_pong(t);
+                let u = 1.0 - t;
+                *a * (u * u * u)
+                    + *b * (3.0 * u * u * t)
+                    + *c * (3.0 * u * t * t)
+                    + *d * (t * t * t)
+            }
+            Path::PingPong { from, to } => *from + (*to - *from) * ping_pong(t),
+        }
+    }
+
+    /// Parses a path from `tokens[index..]`, as written by `to_text`.
+    pub fn parse(tokens: &[&str], index: usize, line: usize) -> Result<Self, String> {
+        let points = |count: usize| {
+            (0..count)
+                .map(|i| parse_vec2(tokens, index + 1 + i * 2, line))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(match tokens.get(index).copied().unwrap_or("") {
+            "loop" => Path::Loop(points((tokens.len().saturating_sub(index + 1)) / 2)?),
+            "orbit" => Path::Orbit {
+                center: parse_vec2(tokens, index + 1, line)?,
+                radius: parse_token(tokens, index + 3, line)?,
+            },
+            "bezier" => {
+                let p = points(4)?;
+                Path::Bezier([p[0], p[1], p[2], p[3]])
+            }
+            "ping_pong" => Path::PingPong {
+                from: parse_vec2(tokens, index + 1, line)?,
+                to: parse_vec2(tokens, index + 3, line)?,
+            },
+            other => return Err(format!("Line {}: unknown path {:?}", line, other)),
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let points_text = |points: &[Vec2]| {
+            points
+                .iter()
+                .map(|p| format!(" {:?} {:?}", p.x, p.y))
+                .collect::<String>()
+        };
+
+        match self {
+            Path::Loop(points) => format!("loop{}", points_text(points)),
+            Path::Orbit { center, radius } => {
+                format!("orbit {:?} {:?} {:?}", center.x, center.y, radius)
+            }
+            Path::Bezier(points) => format!("bezier{}", points_text(points)),
+            Path::PingPong { from, to } => format!("ping_pong{}", points_text(&[*from, *to])),
+        }
+    }
+}
+
+/// Goes from 0 to 1 and back as `t` goes from 0 to 1, easing at both ends.
+fn ping_pong(t: f32) -> f32 {
+    let t = if t < 0.5 { t * 2.0 } else { 2.0 - t * 2.0 };
+    animation_curves::smooth_step(t)
+}
+
+/// Moves a kinematic peg or brick along a `Path`.
+/// `RapierIntegration` moves kinematic bodies with `set_next_kinematic_translation`,
+/// so balls are pushed by moving pegs instead of passing through them.
+#[derive(Clone, Debug)]
+pub struct PathMover {
+    pub path: Path,
+    /// Seconds for one trip around the path.
+    pub period: f32,
+    /// How far around the path the entity is, from 0 to 1.
+    pub t: f32,
+}
+
+impl PathMover {
+    /// Parses `<period> <t> <path>` from `tokens[index..]`, as written by `to_text`.
+    pub fn parse(tokens: &[&str], index: usize, line: usize) -> Result<Self, String> {
+        Ok(Self {
+            period: parse_token(tokens, index, line)?,
+            t: parse_token(tokens, index + 1, line)?,
+            path: Path::parse(tokens, index + 2, line)?,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("{:?} {:?} {}", self.period, self.t, self.path.to_text())
+    }
+}
+
+pub fn run_path_movers(world: &mut World, time_step: f32) {
+    for (_, (transform, mover)) in world.query_mut::<(&mut Transform, &mut PathMover)>() {
+        if mover.period > 0.0 {
+            mover.t = (mover.t + time_step / mover.period).fract();
+        }
+        let position = mover.path.position(mover.t);
+        transform.position = position.extend(transform.position.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> PathMover {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        PathMover::parse(&tokens, 0, 1).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for text in [
+            "2.0 0.0 loop 0.0 0.0 10.0 0.0 10.0 10.0",
+            "4.0 0.25 orbit 1.0 2.0 5.0",
+            "1.5 0.5 bezier 0.0 0.0 1.0 1.0 2.0 1.0 3.0 0.0",
+            "5.0 0.0 ping_pong -80.0 -30.0 -55.0 -30.0",
+        ] {
+            assert_eq!(parse(text).to_text(), text);
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let orbit = Path::Orbit {
+            center: Vec2::new(1.0, 2.0),
+            radius: 5.0,
+        };
+        assert_eq!(orbit.position(0.0), Vec2::new(6.0, 2.0));
+
+        let from = Vec2::new(-10.0, 0.0);
+        let to = Vec2::new(10.0, 0.0);
+        let ping_pong = Path::PingPong { from, to };
+        assert_eq!(ping_pong.position(0.0), from);
+        assert_eq!(ping_pong.position(0.5), to);
+
+        let square = Path::Loop(vec![Vec2::ZERO, Vec2::new(10.0, 0.0)]);
+        assert_eq!(square.position(0.25), Vec2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn errors() {
+        let tokens = ["1", "0", "spiral", "0", "0"];
+        assert!(PathMover::parse(&tokens, 0, 1).is_err());
+        let tokens = ["1", "0", "orbit", "0", "0"];
+        assert!(PathMover::parse(&tokens, 0, 1).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::*;
+use std::sync::Arc;
+
+/// When a powerup bought in the shop takes effect.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerupTiming {
+    /// Applied to the board when leaving the shop.
+    OnShopExit,
+    /// Applied to the next ball fired on the board.
+    NextBall,
+}
+
+/// The parts of a ball a `PowerupTiming::NextBall` powerup can change.
+pub struct BallSettings {
+    pub size: f32,
+    pub health_subtract_rate: f32,
+    /// How many bounces the aim preview shows for this ball.
+    pub preview_bounces: usize,
+}
+
+/// A kind of powerup that can be bought in the shop.
+/// Register new ones with `PowerupRegistry::register`, or describe them in `assets/powerups.txt`
+/// if they can be built from one of the existing kinds.
+pub trait PowerupDefinition: Send + Sync {
+    /// A unique name used in data files and saves.
+    fn name(&self) -> &str;
+    /// Shown on the shop brick.
+    fn description(&self) -> &str;
+    /// How many hits it takes to buy.
+    fn cost(&self) -> i32;
+    fn timing(&self) -> PowerupTiming;
+    fn apply_to_board(&self, _world: &mut World, _resources: &mut Resources) {}
+    fn apply_to_ball(&self, _ball: &mut BallSettings) {}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PowerupId(usize);
+
+pub struct PowerupRegistry {
+    powerups: Vec<Arc<dyn PowerupDefinition>>,
+}
+
+pub const POWERUPS_PATH: &str = "assets/powerups.txt";
+const DEFAULT_POWERUPS: &str = include_str!("../assets/powerups.txt");
+
+impl PowerupRegistry {
+    pub fn new() -> Self {
+        Self {
+            powerups: Vec::new(),
+        }
+    }
+
+    /// Loads the powerups listed in `assets/powerups.txt`.
+    pub fn load() -> Self {
+        let mut registry = Self::new();
+        let source = read_data_file(POWERUPS_PATH, DEFAULT_POWERUPS);
+        if let Err(e) = registry.add_from_data(&source) {
+            println!("Could not load {:?}: {}", POWERUPS_PATH, e);
+        }
+        registry
+    }
+
+    /// Registers a powerup, replacing any existing one with the same name.
+    pub fn register(&mut self, powerup: impl PowerupDefinition + 'static) -> PowerupId {
+        if let Some(id) = self.find(powerup.name()) {
+            self.powerups[id.0] = Arc::new(powerup);
+            return id;
+        }
+        self.powerups.push(Arc::new(powerup));
+        PowerupId(self.powerups.len() - 1)
+    }
+
+    /// Returns a shared handle so the registry doesn't stay borrowed while the powerup runs.
+    pub fn get(&self, id: PowerupId) -> Arc<dyn PowerupDefinition> {
+        self.powerups[id.0].clone()
+    }
+
+    pub fn find(&self, name: &str) -> Option<PowerupId> {
+        self.powerups
+            .iter()
+            .position(|p| p.name() == name)
+            .map(PowerupId)
+    }
+
+    pub fn ids(&self) -> Vec<PowerupId> {
+        (0..self.powerups.len()).map(PowerupId).collect()
+    }
+
+    /// See `assets/powerups.txt` for the format.
+    pub fn add_from_data(&mut self, source: &str) -> Result<(), String> {
+        let mut current: Option<DataPowerup> = None;
+
+        for (line, tokens) in data_lines(source) {
+            let peg_type = |index: usize| {
+                let name = tokens.get(index).copied().unwrap_or("");
+                PegType::from_name(name)
+                    .ok_or_else(|| format!("Line {}: unknown peg type {:?}", line, name))
+            };
+
+            if tokens[0] == "powerup" {
+                if let Some(powerup) = current.take() {
+                    self.register(powerup);
+                }
+                current = Some(DataPowerup {
+                    name: parse_token(&tokens, 1, line)?,
+                    description: String::new(),
+                    cost: 10,
+                    kind: PowerupKind::BiggerBall { size: 2.0 },
+                });
+                continue;
+            }
+
+            let powerup = current
+                .as_mut()
+                .ok_or_else(|| format!("Line {}: expected 'powerup <name>' first", line))?;
+
+            match tokens[0] {
+                "description" => powerup.description = tokens[1..].join(" "),
+                "cost" => powerup.cost = parse_token(&tokens, 1, line)?,
+                "bigger_ball" => {
+                    powerup.kind = PowerupKind::BiggerBall {
+                        size: parse_token(&tokens, 1, line)?,
+                    }
+                }
+                "rock_wall" => powerup.kind = PowerupKind::RockWall,
+                "longer_aim" => {
+                    powerup.kind = PowerupKind::LongerAim {
+                        bounces: parse_token(&tokens, 1, line)?,
+                    }
+                }
+                "transform" => {
+                    powerup.kind = PowerupKind::Transform {
+                        from: peg_type(1)?,
+                        to: peg_type(2)?,
+                    }
+                }
+                "storm" => {
+                    powerup.kind = PowerupKind::Storm(Storm {
+                        peg_type: peg_type(1)?,
+                        center_min: Vec2::new(-30.0, -40.0),
+                        center_max: Vec2::new(30.0, 30.0),
+                        turn_rate: (0.05, 0.05),
+                        radius_rate: (1.5, 1.5),
+                        count: (20, 20),
+                        start_radius: 10.0,
+                    })
+                }
+                "center" | "turn_rate" | "radius_rate" | "count" | "start_radius" => {
+                    let storm = match &mut powerup.kind {
+                        PowerupKind::Storm(storm) => storm,
+                        _ => return Err(format!("Line {}: only storms have a {}", line, tokens[0])),
+                    };
+                    match tokens[0] {
+                        "center" => {
+                            storm.center_min = parse_vec2(&tokens, 1, line)?;
+                            storm.center_max = parse_vec2(&tokens, 3, line)?;
+                        }
+                        "turn_rate" => {
+                            storm.turn_rate =
+                                (parse_token(&tokens, 1, line)?, parse_token(&tokens, 2, line)?)
+                        }
+                        "radius_rate" => {
+                            storm.radius_rate =
+                                (parse_token(&tokens, 1, line)?, parse_token(&tokens, 2, line)?)
+                        }
+                        "count" => {
+                            storm.count =
+                                (parse_token(&tokens, 1, line)?, parse_token(&tokens, 2, line)?)
+                        }
+                        _ => storm.start_radius = parse_token(&tokens, 1, line)?,
+                    }
+                }
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+
+        if let Some(powerup) = current.take() {
+            self.register(powerup);
+        }
+        Ok(())
+    }
+}
+
+/// A powerup described in a data file.
+struct DataPowerup {
+    name: String,
+    description: String,
+    cost: i32,
+    kind: PowerupKind,
+}
+
+enum PowerupKind {
+    BiggerBall { size: f32 },
+    Storm(Storm),
+    Transform { from: PegType, to: PegType },
+    RockWall,
+    LongerAim { bounces: usize },
+}
+
+/// Spawns a spiral of pegs somewhere on the board with `apply_rock_storm`.
+/// Each range is (min, max) and picked from when the storm is applied.
+struct Storm {
+    peg_type: PegType,
+    center_min: Vec2,
+    center_max: Vec2,
+    turn_rate: (f32, f32),
+    radius_rate: (f32, f32),
+    count: (u32, u32),
+    start_radius: f32,
+}
+
+/// Equal bounds, which `range_f32` would reject as an empty range, always give `min`.
+pub fn pick_f32(random: &mut Random, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        random.range_f32(min..max)
+    } else {
+        min
+    }
+}
+
+impl PowerupDefinition for DataPowerup {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn cost(&self) -> i32 {
+        self.cost
+    }
+
+    fn timing(&self) -> PowerupTiming {
+        match self.kind {
+            PowerupKind::BiggerBall { .. } | PowerupKind::LongerAim { .. } => {
+                PowerupTiming::NextBall
+            }
+            _ => PowerupTiming::OnShopExit,
+        }
+    }
+
+    fn apply_to_board(&self, world: &mut World, resources: &mut Resources) {
+        match &self.kind {
+            PowerupKind::BiggerBall { .. } | PowerupKind::LongerAim { .. } => {}
+            PowerupKind::RockWall => create_rock_wall(world),
+            PowerupKind::Transform { from, to } => {
+                apply_rocks_to_gold(world, resources, *from, *to)
+            }
+            PowerupKind::Storm(storm) => {
+                let (center, turn_rate, radius_rate, count) = {
+                    let mut random = resources.get::<GameRandom>();
+                    let center = Vec2::new(
+                        pick_f32(&mut random, (storm.center_min.x, storm.center_max.x)),
+                        pick_f32(&mut random, (storm.center_min.y, storm.center_max.y)),
+                    );
+                    let turn_rate = pick_f32(&mut random, storm.turn_rate);
+                    let radius_rate = pick_f32(&mut random, storm.radius_rate);
+                    let count = if storm.count.0 < storm.count.1 {
+                        random.range_u32(storm.count.0..storm.count.1)
+                    } else {
+                        storm.count.0
+                    };
+                    (center, turn_rate, radius_rate, count)
+                };
+                apply_rock_storm(
+                    world,
+                    resources,
+                    storm.peg_type,
+                    turn_rate,
+                    radius_rate,
+                    count as _,
+                    center,
+                    storm.start_radius,
+                )
+            }
+        }
+    }
+
+    fn apply_to_ball(&self, ball: &mut BallSettings) {
+        match self.kind {
+            PowerupKind::BiggerBall { size } => {
+                ball.size *= size;
+                ball.health_subtract_rate *= size;
+            }
+            PowerupKind::LongerAim { bounces } => ball.preview_bounces += bounces,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_powerups_parse() {
+        let mut registry = PowerupRegistry::new();
+        registry.add_from_data(DEFAULT_POWERUPS).unwrap();
+        let big_ball = registry.get(registry.find("BigBall").unwrap());
+        assert_eq!(big_ball.cost(), 3);
+        assert_eq!(big_ball.description(), "Bigger Ball");
+        assert_eq!(big_ball.timing(), PowerupTiming::NextBall);
+        let storm = registry.get(registry.find("RockStorm").unwrap());
+        assert_eq!(storm.timing(), PowerupTiming::OnShopExit);
+    }
+
+    #[test]
+    fn later_powerups_replace_earlier_ones() {
+        let mut registry = PowerupRegistry::new();
+        registry
+            .add_from_data("powerup A\ncost 1\npowerup A\ncost 2")
+            .unwrap();
+        assert_eq!(registry.ids().len(), 1);
+        assert_eq!(registry.get(registry.find("A").unwrap()).cost(), 2);
+    }
+
+    #[test]
+    fn bigger_ball() {
+        let mut registry = PowerupRegistry::new();
+        registry
+            .add_from_data("powerup Big\nbigger_ball 3")
+            .unwrap();
+        let mut ball = BallSettings {
+            size: 2.0,
+            health_subtract_rate: 1.0,
+            preview_bounces: 1,
+        };
+        registry
+            .get(registry.find("Big").unwrap())
+            .apply_to_ball(&mut ball);
+        assert_eq!(ball.size, 6.0);
+        assert_eq!(ball.health_subtract_rate, 3.0);
+    }
+
+    #[test]
+    fn errors() {
+        let mut registry = PowerupRegistry::new();
+        assert!(registry.add_from_data("cost 3").is_err());
+        assert!(registry
+            .add_from_data("powerup A\nbigger_ball 2\nturn_rate 1 2")
+            .is_err());
+        assert!(registry.add_from_data("powerup A\nfly").is_err());
+    }
+
+    #[test]
+    fn pick_f32_with_equal_bounds() {
+        let mut random = Random::new_with_seed(1);
+        assert_eq!(pick_f32(&mut random, (2.0, 2.0)), 2.0);
+    }
+}
@@ -1,14 +1,56 @@
 use koi3::*;
+use rapier2d::crossbeam::channel::Receiver;
+use rapier2d::parry::shape::{Ball, Shape, TypedShape};
 use rapier2d::prelude::*;
 
 pub struct RapierRigidBody {
     rigid_body_handle: rapier2d::prelude::RigidBodyHandle,
-    pub collider_handle: rapier2d::prelude::ColliderHandle,
+    /// Every collider attached to the body. Most bodies only have one.
+    pub collider_handles: Vec<rapier2d::prelude::ColliderHandle>,
+    /// Each collider's shape and offset as it was created, so scaling doesn't accumulate error.
+    original_shapes: Vec<(SharedShape, Isometry<Real>)>,
+    /// The `Transform::scale` the original shapes were made for.
+    /// Set the first time the body is updated.
+    original_scale: Option<Vec3>,
     scale: Vec3,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ContactEventKind {
+    Started,
+    Stopped,
+    /// A sensor collider started overlapping another collider.
+    IntersectionStarted,
+    IntersectionStopped,
+}
+
+/// A collision reported during the last `RapierIntegration::step`.
+#[derive(Clone, Copy, Debug)]
+pub struct ContactEvent {
+    pub kind: ContactEventKind,
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// The total impulse between the two colliders during the step, for how hard they hit.
+    /// Zero for stopped and intersection events.
+    pub impulse: f32,
+    /// A world space point where the colliders touch, if they do.
+    pub point: Option<Vec2>,
+}
+
+/// The result of a ray or shape cast.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryHit {
+    pub entity: Entity,
+    /// Where the ray or shape first touches the entity.
+    pub point: Vec2,
+    /// The entity's surface normal at `point`.
+    pub normal: Vec2,
+    /// The distance along a ray, or multiples of the velocity for a shape cast.
+    pub time_of_impact: f32,
+}
+
 pub struct RapierIntegration {
-    gravity: Vec2,
+    pub gravity: Vec2,
     integration_parameters: IntegrationParameters,
     physics_pipeline: PhysicsPipeline,
     island_manager: IslandManager,
@@ -20,12 +62,21 @@ pub struct RapierIntegration {
     rigid_body_set: RigidBodySet,
     pub collider_set: ColliderSet,
     query_pipeline: QueryPipeline,
+    event_collector: ChannelEventCollector,
+    collision_events: Receiver<CollisionEvent>,
+    _contact_force_events: Receiver<ContactForceEvent>,
+    /// Replaced each `step`.
+    pub contact_events: Vec<ContactEvent>,
 }
 
 pub const GRAVITY: f32 = -9.81 * 8.0;
 
 impl RapierIntegration {
     pub fn new() -> Self {
+        let (collision_sender, collision_events) = rapier2d::crossbeam::channel::unbounded();
+        let (contact_force_sender, contact_force_events) =
+            rapier2d::crossbeam::channel::unbounded();
+
         Self {
             gravity: Vec2::new(0.0, GRAVITY),
             integration_parameters: IntegrationParameters::default(),
@@ -39,10 +90,24 @@ impl RapierIntegration {
             rigid_body_set: RigidBodySet::new(),
             collider_set: ColliderSet::new(),
             query_pipeline: QueryPipeline::new(),
+            event_collector: ChannelEventCollector::new(collision_sender, contact_force_sender),
+            collision_events,
+            _contact_force_events: contact_force_events,
+            contact_events: Vec::new(),
         }
     }
 
-    pub fn add_collider(&mut self, collider: Collider) {
+    /// Changes the bounciness of everything already added. Sensors are left alone.
+    pub fn set_restitution(&mut self, restitution: f32) {
+        for (_, collider) in self.collider_set.iter_mut() {
+            if !collider.is_sensor() {
+                collider.set_restitution(restitution);
+            }
+        }
+    }
+
+    pub fn add_collider(&mut self, mut collider: Collider) {
+        collider.set_active_events(ActiveEvents::COLLISION_EVENTS);
         self.collider_set.insert(collider);
     }
 
@@ -50,20 +115,88 @@ impl RapierIntegration {
         &mut self,
         rigid_body: RigidBody,
         collider: Collider,
+    ) -> RapierRigidBody {
+        self.add_rigid_body_with_colliders(rigid_body, vec![collider])
+    }
+
+    /// Attaches several colliders to one body, for shapes like L-shaped bricks.
+    /// Offset each collider from the body with `ColliderBuilder::translation` and `rotation`.
+    pub fn add_rigid_body_with_colliders(
+        &mut self,
+        rigid_body: RigidBody,
+        colliders: Vec<Collider>,
     ) -> RapierRigidBody {
         let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
-        let collider_handle = self.collider_set.insert_with_parent(
-            collider,
-            rigid_body_handle,
-            &mut self.rigid_body_set,
-        );
+        let original_shapes = colliders
+            .iter()
+            .map(|c| {
+                let offset = c
+                    .position_wrt_parent()
+                    .copied()
+                    .unwrap_or_else(Isometry::identity);
+                (c.shared_shape().clone(), offset)
+            })
+            .collect();
+        let collider_handles = colliders
+            .into_iter()
+            .map(|mut collider| {
+                collider.set_active_events(ActiveEvents::COLLISION_EVENTS);
+                self.collider_set.insert_with_parent(
+                    collider,
+                    rigid_body_handle,
+                    &mut self.rigid_body_set,
+                )
+            })
+            .collect();
         RapierRigidBody {
             rigid_body_handle: rigid_body_handle,
-            collider_handle,
+            collider_handles,
+            original_shapes,
+            original_scale: None,
             scale: Vec3::ONE,
         }
     }
 
+    /// Joins the bodies of two entities, for things like pendulums and hinged gates.
+    /// Build `joint` with `revolute_joint`, `prismatic_joint` or `fixed_joint`.
+    /// The joint is removed along with either body once its entity is despawned.
+    /// Returns `None` if either entity has no body.
+    pub fn add_joint(
+        &mut self,
+        world: &World,
+        entity_a: Entity,
+        entity_b: Entity,
+        joint: impl Into<GenericJoint>,
+    ) -> Option<ImpulseJointHandle> {
+        let body = |e: Entity| {
+            world
+                .get::<&RapierRigidBody>(e)
+                .map(|body| body.rigid_body_handle)
+                .ok()
+        };
+        match (body(entity_a), body(entity_b)) {
+            (Some(a), Some(b)) => Some(self.impulse_joint_set.insert(a, b, joint)),
+            _ => None,
+        }
+    }
+
+    /// Unjoins two bodies without despawning either, like a gate breaking off its hinge.
+    pub fn remove_joint(&mut self, joint: ImpulseJointHandle) {
+        self.impulse_joint_set.remove(
+            joint,
+            &mut self.island_manager,
+            &mut self.rigid_body_set,
+            true,
+        );
+    }
+
+    /// Lets gameplay turn a kinematic body, like a peg, into one that physics moves.
+    pub fn set_body_type(&mut self, world: &World, entity: Entity, body_type: RigidBodyType) {
+        if let Ok(body) = world.get::<&RapierRigidBody>(entity) {
+            self.rigid_body_set[body.rigid_body_handle].set_body_type(body_type);
+        }
+    }
+
     pub fn update_colliders(&mut self, world: &mut World) {
         let mut entities_to_despawn = Vec::new();
 
@@ -72,22 +205,46 @@ impl RapierIntegration {
             .query::<(&mut Transform, &mut RapierRigidBody)>()
             .iter()
         {
+            let first_update = rigid_body.original_scale.is_none();
+            let original_scale = *rigid_body.original_scale.get_or_insert(transform.scale);
             if transform.scale != rigid_body.scale {
                 rigid_body.scale = transform.scale;
+                let factor = Vec2::new(
+                    scale_factor(transform.scale.x, original_scale.x),
+                    scale_factor(transform.scale.y, original_scale.y),
+                );
+                for (collider_handle, (shape, offset)) in rigid_body
+                    .collider_handles
+                    .iter()
+                    .zip(&rigid_body.original_shapes)
+                {
+                    if let Some(shape) = scale_shape(shape, factor) {
+                        let collider = self.collider_set.get_mut(*collider_handle).unwrap();
+                        collider.set_shape(shape);
+                        collider.set_position_wrt_parent(scale_isometry(offset, factor));
+                    }
+                }
+            }
+            for collider_handle in &rigid_body.collider_handles {
+                let collider = self.collider_set.get_mut(*collider_handle).unwrap();
+                collider.user_data = e.to_bits().get() as _;
             }
-            let collider = self
-                .collider_set
-                .get_mut(rigid_body.collider_handle)
-                .unwrap();
-            collider.user_data = e.to_bits().get() as _;
             let body = &mut self.rigid_body_set[rigid_body.rigid_body_handle];
             let p_array: [f32; 2] = body.position().translation.into();
             if p_array != transform.position.xy().as_array() {
                 let new_p: [f32; 2] = transform.position.xy().into();
-                body.set_translation(new_p.into(), true);
+                // Moving kinematic bodies this way lets Rapier give them a velocity,
+                // so they push balls instead of teleporting into them.
+                if body.is_kinematic() && !first_update {
+                    body.set_next_kinematic_translation(new_p.into());
+                } else {
+                    body.set_translation(new_p.into(), true);
+                }
             }
 
-            let angle = transform.rotation.to_angle_axis().0;
+            // Negative rotations come back as a positive angle around -Z.
+            let (angle, axis) = transform.rotation.to_angle_axis();
+            let angle = if axis.z < 0.0 { -angle } else { angle };
             if body.rotation().angle() != angle {
                 body.set_rotation(angle, true);
             }
@@ -114,6 +271,7 @@ impl RapierIntegration {
             }
         }
 
+        // Removing a body also removes any joints attached to it.
         for c in to_despawn {
             self.rigid_body_set.remove(
                 c,
@@ -142,9 +300,11 @@ impl RapierIntegration {
             &mut self.multibody_joint_set,
             &mut self.ccd_solver,
             &(),
-            &(),
+            &self.event_collector,
         );
 
+        self.collect_contact_events();
+
         for (_, (transform, rigid_body)) in
             world.query::<(&mut Transform, &RapierRigidBody)>().iter()
         {
@@ -162,4 +322,376 @@ impl RapierIntegration {
             &self.collider_set,
         );
     }
+
+    fn collider_entity(&self, handle: ColliderHandle) -> Option<Entity> {
+        self.collider_set
+            .get(handle)
+            .filter(|c| c.user_data != 0)
+            .and_then(|c| Entity::from_bits(c.user_data as _))
+    }
+
+    /// Queries pass through sensors, as balls do.
+    fn query_filter(exclude: Option<&RapierRigidBody>) -> QueryFilter {
+        let filter = QueryFilter::default().exclude_sensors();
+        match exclude {
+            Some(body) => filter.exclude_rigid_body(body.rigid_body_handle),
+            None => filter,
+        }
+    }
+
+    /// Finds the first entity along a ray. `direction` doesn't need to be normalized.
+    /// Queries see the world as it was at the end of the last `step`.
+    pub fn cast_ray(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        exclude: Option<&RapierRigidBody>,
+    ) -> Option<QueryHit> {
+        let direction = direction.normalized();
+        let ray = Ray::new(to_point(origin), Vector::new(direction.x, direction.y));
+        let (handle, intersection) = self.query_pipeline.cast_ray_and_get_normal(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            true,
+            Self::query_filter(exclude),
+        )?;
+        Some(QueryHit {
+            entity: self.collider_entity(handle)?,
+            point: origin + direction * intersection.toi,
+            normal: Vec2::new(intersection.normal.x, intersection.normal.y),
+            time_of_impact: intersection.toi,
+        })
+    }
+
+    /// Sweeps `shape` from `origin` along `velocity` and finds the first entity it touches.
+    /// `time_of_impact` is in multiples of `velocity`, up to `max_time`.
+    pub fn cast_shape(
+        &self,
+        origin: Vec2,
+        velocity: Vec2,
+        shape: &dyn Shape,
+        max_time: f32,
+        exclude: Option<&RapierRigidBody>,
+    ) -> Option<QueryHit> {
+        let (handle, toi) = self.query_pipeline.cast_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &Isometry::translation(origin.x, origin.y),
+            &Vector::new(velocity.x, velocity.y),
+            shape,
+            max_time,
+            Self::query_filter(exclude),
+        )?;
+        let collider = self.collider_set.get(handle)?;
+        // The second shape in the impact is the collider that was hit.
+        let normal = collider.position().rotation * *toi.normal2;
+        let point = collider.position() * toi.witness2;
+        Some(QueryHit {
+            entity: self.collider_entity(handle)?,
+            point: Vec2::new(point.x, point.y),
+            normal: Vec2::new(normal.x, normal.y),
+            time_of_impact: toi.toi,
+        })
+    }
+
+    /// Shorthand for `cast_shape` with a ball, like the one fired by the player.
+    pub fn cast_ball(
+        &self,
+        origin: Vec2,
+        velocity: Vec2,
+        radius: f32,
+        max_time: f32,
+        exclude: Option<&RapierRigidBody>,
+    ) -> Option<QueryHit> {
+        self.cast_shape(origin, velocity, &Ball::new(radius), max_time, exclude)
+    }
+
+    /// Every entity with a collider containing `point`, like the peg under the mouse.
+    pub fn entities_at_point(&self, point: Vec2) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.query_pipeline.intersections_with_point(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &to_point(point),
+            QueryFilter::default(),
+            |handle| {
+                entities.extend(self.collider_entity(handle));
+                true
+            },
+        );
+        entities
+    }
+
+    /// Every entity with a collider overlapping a circle, for effects that hit an area.
+    /// Sensors are left out, so a catcher doesn't count as something in the way.
+    pub fn entities_in_circle(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.query_pipeline.intersections_with_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &Isometry::translation(center.x, center.y),
+            &Ball::new(radius),
+            Self::query_filter(None),
+            |handle| {
+                entities.extend(self.collider_entity(handle));
+                true
+            },
+        );
+        entities
+    }
+
+    fn collect_contact_events(&mut self) {
+        self.contact_events.clear();
+
+        while let Ok(event) = self.collision_events.try_recv() {
+            let (collider_a, collider_b) = (event.collider1(), event.collider2());
+            let kind = match (event.started(), event.sensor()) {
+                (true, false) => ContactEventKind::Started,
+                (false, false) => ContactEventKind::Stopped,
+                (true, true) => ContactEventKind::IntersectionStarted,
+                (false, true) => ContactEventKind::IntersectionStopped,
+            };
+
+            // Colliders that were just removed won't be found.
+            let (entity_a, entity_b) = match (
+                self.collider_entity(collider_a),
+                self.collider_entity(collider_b),
+            ) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+
+            let mut impulse = 0.0;
+            let mut point = None;
+            if kind == ContactEventKind::Started {
+                if let Some(contact_pair) = self.narrow_phase.contact_pair(collider_a, collider_b) {
+                    for manifold in &contact_pair.manifolds {
+                        for contact in &manifold.points {
+                            impulse += contact.data.impulse;
+                        }
+                        if point.is_none() {
+                            point = manifold
+                                .data
+                                .solver_contacts
+                                .first()
+                                .map(|c| Vec2::new(c.point.x, c.point.y));
+                        }
+                    }
+                }
+            }
+
+            self.contact_events.push(ContactEvent {
+                kind,
+                entity_a,
+                entity_b,
+                impulse,
+                point,
+            });
+        }
+    }
+}
+
+fn to_point(p: Vec2) -> Point<Real> {
+    Point::new(p.x, p.y)
+}
+
+/// Lets two bodies rotate around a shared point.
+/// Anchors are relative to each body's position.
+pub fn revolute_joint(anchor_a: Vec2, anchor_b: Vec2) -> RevoluteJointBuilder {
+    RevoluteJointBuilder::new()
+        .local_anchor1(to_point(anchor_a))
+        .local_anchor2(to_point(anchor_b))
+}
+
+/// Lets two bodies slide along `axis`, relative to the first body.
+pub fn prismatic_joint(axis: Vec2, anchor_a: Vec2, anchor_b: Vec2) -> PrismaticJointBuilder {
+    let axis = rapier2d::na::Unit::new_normalize(Vector::new(axis.x, axis.y));
+    PrismaticJointBuilder::new(axis)
+        .local_anchor1(to_point(anchor_a))
+        .local_anchor2(to_point(anchor_b))
+}
+
+/// Locks two bodies together.
+pub fn fixed_joint(anchor_a: Vec2, anchor_b: Vec2) -> FixedJointBuilder {
+    FixedJointBuilder::new()
+        .local_anchor1(to_point(anchor_a))
+        .local_anchor2(to_point(anchor_b))
+}
+
+/// A capsule between `a` and `b`, useful for rails and angled bumpers.
+pub fn capsule_collider(a: Vec2, b: Vec2, radius: f32) -> ColliderBuilder {
+    ColliderBuilder::new(SharedShape::capsule(to_point(a), to_point(b), radius))
+}
+
+/// A chain of line segments through `points`, useful for curved rails.
+/// Balls only collide with the lines themselves, so the shape doesn't need to be convex.
+pub fn polyline_collider(points: &[Vec2], closed: bool) -> ColliderBuilder {
+    let vertices = points.iter().map(|p| to_point(*p)).collect();
+    let indices = if closed && points.len() > 2 {
+        let count = points.len() as u32;
+        Some((0..count).map(|i| [i, (i + 1) % count]).collect())
+    } else {
+        None
+    };
+    ColliderBuilder::polyline(vertices, indices)
+}
+
+/// The smallest convex shape containing every point.
+/// Returns `None` if there aren't enough points for a hull.
+pub fn convex_hull_collider(points: &[Vec2]) -> Option<ColliderBuilder> {
+    let points: Vec<_> = points.iter().map(|p| to_point(*p)).collect();
+    ColliderBuilder::convex_hull(&points)
+}
+
+/// Finds the outline of the opaque part of a sprite, as points for `convex_hull_collider`.
+/// `rgba` is the sprite's pixels, top row first.
+/// Points are in the space of `Mesh::VERTICAL_QUAD`, from -0.5 to 0.5, so they're sized by
+/// the entity's `Transform::scale` like the sprite is.
+pub fn sprite_outline(width: usize, height: usize, rgba: &[u8], alpha_threshold: u8) -> Vec<Vec2> {
+    let mut points = Vec::new();
+    let corner = |x: usize, y: usize| {
+        Vec2::new(
+            x as f32 / width as f32 - 0.5,
+            0.5 - y as f32 / height as f32,
+        )
+    };
+    // The outer corners of the leftmost and rightmost opaque pixel in each row are enough
+    // for the convex hull to find the rest.
+    for y in 0..height {
+        let row = &rgba[y * width * 4..(y + 1) * width * 4];
+        let opaque = |x: &usize| row[x * 4 + 3] >= alpha_threshold;
+        let left = (0..width).find(opaque);
+        let right = (0..width).rev().find(opaque);
+        if let (Some(left), Some(right)) = (left, right) {
+            points.push(corner(left, y));
+            points.push(corner(left, y + 1));
+            points.push(corner(right + 1, y));
+            points.push(corner(right + 1, y + 1));
+        }
+    }
+    points
+}
+
+fn scale_factor(scale: f32, original_scale: f32) -> f32 {
+    if original_scale == 0.0 {
+        1.0
+    } else {
+        scale / original_scale
+    }
+}
+
+fn scale_point(p: &Point<Real>, factor: Vec2) -> Point<Real> {
+    Point::new(p.x * factor.x, p.y * factor.y)
+}
+
+fn scale_isometry(isometry: &Isometry<Real>, factor: Vec2) -> Isometry<Real> {
+    let mut isometry = *isometry;
+    isometry.translation.x *= factor.x;
+    isometry.translation.y *= factor.y;
+    isometry
+}
+
+/// Returns `None` for shapes that can't be scaled, which are left as they are.
+/// Round shapes can't be stretched, so they're scaled by the larger axis.
+fn scale_shape(shape: &SharedShape, factor: Vec2) -> Option<SharedShape> {
+    let radius_factor = factor.x.abs().max(factor.y.abs());
+    Some(match shape.as_typed_shape() {
+        TypedShape::Ball(ball) => SharedShape::ball(ball.radius * radius_factor),
+        TypedShape::Cuboid(cuboid) => SharedShape::cuboid(
+            cuboid.half_extents.x * factor.x.abs(),
+            cuboid.half_extents.y * factor.y.abs(),
+        ),
+        TypedShape::Capsule(capsule) => SharedShape::capsule(
+            scale_point(&capsule.segment.a, factor),
+            scale_point(&capsule.segment.b, factor),
+            capsule.radius * radius_factor,
+        ),
+        TypedShape::Polyline(polyline) => SharedShape::polyline(
+            polyline
+                .vertices()
+                .iter()
+                .map(|p| scale_point(p, factor))
+                .collect(),
+            Some(polyline.indices().to_vec()),
+        ),
+        TypedShape::ConvexPolygon(polygon) => {
+            let points: Vec<_> = polygon
+                .points()
+                .iter()
+                .map(|p| scale_point(p, factor))
+                .collect();
+            SharedShape::convex_hull(&points)?
+        }
+        TypedShape::Compound(compound) => SharedShape::compound(
+            compound
+                .shapes()
+                .iter()
+                .map(|(isometry, shape)| {
+                    let shape = scale_shape(shape, factor).unwrap_or_else(|| shape.clone());
+                    (scale_isometry(isometry, factor), shape)
+                })
+                .collect(),
+        ),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprite_outline_hull() {
+        // A 4 by 4 sprite with only the middle 2 by 2 pixels opaque.
+        let mut rgba = vec![0; 4 * 4 * 4];
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+            rgba[(y * 4 + x) * 4 + 3] = 255;
+        }
+        let outline = sprite_outline(4, 4, &rgba, 128);
+        assert_eq!(outline.len(), 8);
+        for p in &outline {
+            assert!(p.x.abs() <= 0.25 && p.y.abs() <= 0.25);
+        }
+        assert!(convex_hull_collider(&outline).is_some());
+        assert!(sprite_outline(4, 4, &[0; 4 * 4 * 4], 128).is_empty());
+    }
+
+    #[test]
+    fn casts() {
+        let mut world = World::new();
+        let mut rapier_integration = RapierIntegration::new();
+        let body = rapier_integration.add_rigid_body_with_collider(
+            RigidBodyBuilder::fixed().build(),
+            ColliderBuilder::ball(1.0).build(),
+        );
+        let peg = world.spawn((
+            Transform::new().with_position(Vec3::new(0.0, 10.0, 0.0)),
+            body,
+        ));
+        // Queries only see bodies once they've been stepped.
+        rapier_integration.step(&mut world);
+
+        let hit = rapier_integration
+            .cast_ray(Vec2::ZERO, Vec2::new(0.0, 2.0), 100.0, None)
+            .unwrap();
+        assert!(hit.entity == peg);
+        assert!((hit.point - Vec2::new(0.0, 9.0)).length() < 0.01);
+        assert!((hit.normal - Vec2::new(0.0, -1.0)).length() < 0.01);
+        assert!((hit.time_of_impact - 9.0).abs() < 0.01);
+        assert!(rapier_integration
+            .cast_ray(Vec2::ZERO, Vec2::new(1.0, 0.0), 100.0, None)
+            .is_none());
+        assert!(rapier_integration
+            .cast_ray(Vec2::ZERO, Vec2::new(0.0, 1.0), 5.0, None)
+            .is_none());
+
+        let hit = rapier_integration
+            .cast_ball(Vec2::ZERO, Vec2::new(0.0, 1.0), 1.0, 100.0, None)
+            .unwrap();
+        assert!((hit.point - Vec2::new(0.0, 9.0)).length() < 0.01);
+        assert!((hit.time_of_impact - 8.0).abs() < 0.01);
+    }
 }
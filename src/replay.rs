@@ -0,0 +1,211 @@
+use crate::*;
+
+/// Everything needed to reproduce a run: the seed, the level and each input with the
+/// fixed update tick it happened on.
+pub struct Replay {
+    pub seed: u64,
+    pub level_path: String,
+    pub events: Vec<(u64, ReplayEvent)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ReplayEvent {
+    Shot {
+        direction: Vec2,
+    },
+    ToggleShop,
+    /// Moves on from the round summary.
+    NextBoard,
+    /// Starts a new run after a game over or win.
+    Restart,
+    /// Loads the saved run. Saves don't hold the random state or the save file itself,
+    /// so playback stops here.
+    LoadRun,
+}
+
+pub const REPLAY_PATH: &str = "replay.txt";
+
+impl Replay {
+    pub fn new(seed: u64, level_path: &str) -> Self {
+        Self {
+            seed,
+            level_path: level_path.to_string(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::parse(&read_data_file(path, "")).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut replay = Replay::new(0, DEFAULT_LEVEL_PATH);
+        for (line, tokens) in data_lines(source) {
+            match tokens[0] {
+                "seed" => replay.seed = parse_token(&tokens, 1, line)?,
+                "level" => replay.level_path = parse_token(&tokens, 1, line)?,
+                "shot" => replay.events.push((
+                    parse_token(&tokens, 1, line)?,
+                    ReplayEvent::Shot {
+                        direction: parse_vec2(&tokens, 2, line)?,
+                    },
+                )),
+                "shop" => replay
+                    .events
+                    .push((parse_token(&tokens, 1, line)?, ReplayEvent::ToggleShop)),
+                "restart" => replay
+                    .events
+                    .push((parse_token(&tokens, 1, line)?, ReplayEvent::Restart)),
+                "next_board" => replay
+                    .events
+                    .push((parse_token(&tokens, 1, line)?, ReplayEvent::NextBoard)),
+                "load_run" => replay
+                    .events
+                    .push((parse_token(&tokens, 1, line)?, ReplayEvent::LoadRun)),
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+        Ok(replay)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seed {}\nlevel {}\n", self.seed, self.level_path);
+        for (tick, event) in &self.events {
+            text.push_str(&event_text(*tick, event));
+        }
+        text
+    }
+}
+
+/// One line of a replay file.
+fn event_text(tick: u64, event: &ReplayEvent) -> String {
+    // `{:?}` prints the shortest float that parses back to exactly the same bits.
+    match event {
+        ReplayEvent::Shot { direction } => {
+            format!("shot {} {:?} {:?}\n", tick, direction.x, direction.y)
+        }
+        ReplayEvent::ToggleShop => format!("shop {}\n", tick),
+        ReplayEvent::NextBoard => format!("next_board {}\n", tick),
+        ReplayEvent::Restart => format!("restart {}\n", tick),
+        ReplayEvent::LoadRun => format!("load_run {}\n", tick),
+    }
+}
+
+/// Records the current run and optionally plays back a previous one.
+pub struct ReplayState {
+    /// The number of fixed updates run so far.
+    pub tick: u64,
+    pub recording: Replay,
+    playback: Option<Replay>,
+    playback_index: usize,
+}
+
+impl ReplayState {
+    pub fn new(recording: Replay, playback: Option<Replay>) -> Self {
+        Self {
+            tick: 0,
+            recording,
+            playback,
+            playback_index: 0,
+        }
+    }
+
+    pub fn playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Records an input on the current tick and adds it to the replay file.
+    /// The first input replaces any replay left from a previous launch.
+    pub fn record(&mut self, event: ReplayEvent) {
+        self.recording.events.push((self.tick, event));
+        if self.recording.events.len() == 1 {
+            write_data_file(REPLAY_PATH, &self.recording.to_text());
+        } else {
+            append_data_file(REPLAY_PATH, &event_text(self.tick, &event));
+        }
+    }
+
+    /// The played back events that should happen before the current tick is simulated.
+    pub fn events_for_tick(&mut self) -> Vec<ReplayEvent> {
+        let mut events = Vec::new();
+        if let Some(playback) = &self.playback {
+            while let Some((tick, event)) = playback.events.get(self.playback_index) {
+                if *tick > self.tick {
+                    break;
+                }
+                if let ReplayEvent::LoadRun = event {
+                    println!("The replay loaded a saved run here, so it can't be played further");
+                    self.playback_index = playback.events.len();
+                    break;
+                }
+                events.push(*event);
+                self.playback_index += 1;
+            }
+        }
+        events
+    }
+}
+
+/// Handles `--replay <path>`.
+pub fn replay_from_command_line() -> Option<Replay> {
+    let mut args = std::env::args().skip_while(|a| a != "--replay");
+    args.next()?;
+    let path = args.next().unwrap_or_else(|| REPLAY_PATH.to_string());
+    match Replay::load(&path) {
+        Ok(replay) => Some(replay),
+        Err(e) => {
+            println!("Could not load replay: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(42, "assets/levels/orchard.level");
+        replay.events.push((
+            3,
+            ReplayEvent::Shot {
+                direction: Vec2::new(0.1, -0.7),
+            },
+        ));
+        replay.events.push((10, ReplayEvent::ToggleShop));
+        replay.events.push((20, ReplayEvent::NextBoard));
+        replay.events.push((30, ReplayEvent::Restart));
+        replay.events.push((40, ReplayEvent::LoadRun));
+
+        let parsed = Replay::parse(&replay.to_text()).unwrap();
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.level_path, "assets/levels/orchard.level");
+        assert_eq!(parsed.events.len(), 5);
+        match parsed.events[0] {
+            (3, ReplayEvent::Shot { direction }) => {
+                // Shots have to replay exactly, not just closely.
+                assert_eq!(direction, Vec2::new(0.1, -0.7));
+            }
+            _ => panic!("expected a shot"),
+        }
+        assert!(matches!(parsed.events[4], (40, ReplayEvent::LoadRun)));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Replay::parse("jump 3").is_err());
+        assert!(Replay::parse("shot three 0 1").is_err());
+    }
+
+    #[test]
+    fn playback_stops_at_load() {
+        let playback = Replay::parse("seed 1\nshop 0\nload_run 1\nshop 2").unwrap();
+        let mut state = ReplayState::new(Replay::new(1, DEFAULT_LEVEL_PATH), Some(playback));
+        assert_eq!(state.events_for_tick().len(), 1);
+        for tick in 1..4 {
+            state.tick = tick;
+            assert!(state.events_for_tick().is_empty());
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::*;
+
+/// What happened during a round, shown in the summary after its last ball.
+/// `LevelState` also keeps one for the whole run, shown on game over.
+pub struct RoundStats {
+    pub shots: usize,
+    /// Hits per peg type, in the order each type was first hit.
+    pub pegs_hit: Vec<(PegType, usize)>,
+    pub gold_earned: i32,
+    pub best_multiplier: f32,
+    /// The most pegs hit by a single shot.
+    pub longest_combo: usize,
+}
+
+impl RoundStats {
+    pub fn new() -> Self {
+        Self {
+            shots: 0,
+            pegs_hit: Vec::new(),
+            gold_earned: 0,
+            best_multiplier: 1.0,
+            longest_combo: 0,
+        }
+    }
+
+    pub fn add_peg_hit(&mut self, peg_type: PegType) {
+        match self.pegs_hit.iter_mut().find(|(t, _)| *t == peg_type) {
+            Some((_, count)) => *count += 1,
+            None => self.pegs_hit.push((peg_type, 1)),
+        }
+    }
+
+    /// Called when every ball from a shot has left the board.
+    pub fn add_shot(&mut self, pegs_hit: usize, gold_earned: i32, multiplier: f32) {
+        self.shots += 1;
+        self.gold_earned += gold_earned;
+        self.best_multiplier = self.best_multiplier.max(multiplier);
+        self.longest_combo = self.longest_combo.max(pegs_hit);
+    }
+
+    fn stat_lines(&self) -> Vec<String> {
+        let pegs_hit = if self.pegs_hit.is_empty() {
+            "none".to_string()
+        } else {
+            self.pegs_hit
+                .iter()
+                .map(|(peg_type, count)| format!("{} {}", peg_type.name(), count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        vec![
+            format!("Shots: {}", self.shots),
+            format!("Pegs hit: {}", pegs_hit),
+            format!("Gold earned: {}", self.gold_earned),
+            format!("Best multiplier: x{}", self.best_multiplier),
+            format!("Longest combo: {}", self.longest_combo),
+        ]
+    }
+
+    pub fn summary_text(&self) -> String {
+        let mut lines = vec!["Round over!".to_string()];
+        lines.extend(self.stat_lines());
+        lines.push("Press Space for the next board".to_string());
+        lines.join("\n")
+    }
+
+    pub fn game_over_text(&self, boards_played: usize) -> String {
+        let mut lines = vec![
+            "Out of gold! Game over.".to_string(),
+            format!("Boards played: {}", boards_played),
+        ];
+        lines.extend(self.stat_lines());
+        lines.push("Press R to start a new run".to_string());
+        lines.join("\n")
+    }
+}
+
+/// Replaces the board and shop with the level after the current one,
+/// or a fresh copy of the current one if it's the last.
+/// Gold carries over.
+pub fn start_next_board(world: &mut World, resources: &mut Resources) {
+    let path = {
+        let level_state = resources.get::<LevelState>();
+        if !level_state.round_over {
+            return;
+        }
+        level_state
+            .next_level
+            .clone()
+            .unwrap_or_else(|| level_state.level_path.clone())
+    };
+    load_board(world, resources, &path);
+}
+
+/// Reloads the current level from disk, for editing levels while the game runs.
+/// Only done between shots on the board so nothing in flight is lost.
+/// Returns false if it has to wait.
+pub fn reload_board(world: &mut World, resources: &mut Resources) -> bool {
+    let path = {
+        let level_state = resources.get::<LevelState>();
+        if !level_state.ready_to_shoot || level_state.in_shop {
+            return false;
+        }
+        level_state.level_path.clone()
+    };
+    load_board(world, resources, &path);
+    true
+}
+
+fn load_board(world: &mut World, resources: &mut Resources, path: &str) {
+    let level = match Level::load(path) {
+        Ok(level) => level,
+        Err(e) => {
+            println!("Could not load level: {}", e);
+            return;
+        }
+    };
+
+    // Boards are only loaded from the board, so `world` isn't the shop.
+    let mut level_state = resources.remove::<LevelState>().unwrap();
+    clear_run_entities(world);
+    clear_run_entities(&mut level_state.other_world);
+    level.spawn_elements(world, &mut level_state.other_world, resources);
+
+    level_state.start_level(&level);
+    level_state.multiplier = 1.0;
+    level_state.effects_to_apply_to_next_ball.clear();
+    level_state.collected_pegs.clear();
+    resources.add(level_state);
+
+    resources.get::<UIState>().round_summary = None;
+}
+
+/// Ends the run if the player can't afford another shot and has no gold on the way.
+pub fn check_for_defeat(resources: &mut Resources) {
+    let mut level_state = resources.get::<LevelState>();
+    let mut ui_state = resources.get::<UIState>();
+    if level_state.game_over || level_state.victory || !level_state.ready_to_shoot {
+        return;
+    }
+    if ui_state.incoming_gold > 0 || ui_state.gold >= SHOT_COST {
+        return;
+    }
+
+    level_state.game_over = true;
+    ui_state.current_text = "Out of gold...".into();
+    ui_state.game_over = Some(
+        level_state
+            .run_stats
+            .game_over_text(level_state.boards_played),
+    );
+}
+
+/// A new run can be started between shots, or once the run is over.
+pub fn can_restart(resources: &Resources) -> bool {
+    let level_state = resources.get::<LevelState>();
+    level_state.ready_to_shoot || level_state.game_over || level_state.victory
+}
+
+/// Starts a new run from the level the recording started on,
+/// with the starting gold and a fresh `LevelState`.
+pub fn restart_run(world: &mut World, resources: &mut Resources) {
+    if !can_restart(resources) {
+        return;
+    }
+    let level_path = resources.get::<ReplayState>().recording.level_path.clone();
+    start_new_run(world, resources, &level_path);
+    resources.get::<UIState>().current_text = "Welcome back to my farm!".into();
+}
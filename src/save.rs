@@ -0,0 +1,648 @@
+use crate::*;
+
+pub const SAVE_PATH: &str = "save.txt";
+
+/// An in-progress run.
+/// Only saved between shots once collected pegs are cleared:
+/// pending `DelayedAction`s (like a plant still growing) aren't kept.
+pub struct SaveGame {
+    /// The board being played, so the run carries on to the same next board.
+    pub level_path: String,
+    pub next_level: Option<String>,
+    pub aim_preview_bounces: usize,
+    pub gold: i32,
+    pub incoming_gold: i32,
+    pub multiplier: f32,
+    pub balls_remaining: Option<usize>,
+    /// Powerup names, as they're saved by name rather than `PowerupId`.
+    pub next_ball_effects: Vec<String>,
+    pub pegs: Vec<SavedPeg>,
+    pub stems: Vec<(Vec2, Vec2)>,
+    pub bricks: Vec<SavedBrick>,
+    /// The two ends of each rail piece.
+    pub rails: Vec<(Vec2, Vec2)>,
+    /// The position and parts of each `CompoundBrick`.
+    pub compound_bricks: Vec<(Vec2, Vec<(Vec2, Vec2)>)>,
+    /// The width of each `Catcher` and where it is along its path.
+    pub catchers: Vec<(f32, PathMover)>,
+    pub shop_bricks: Vec<SavedPowerup>,
+}
+
+pub struct SavedPeg {
+    pub position: Vec2,
+    pub peg_type: PegType,
+    pub mover: Option<PathMover>,
+    /// Where the peg hangs from, if it's a `Pendulum`.
+    pub pivot: Option<Vec2>,
+    /// The species name of a `GrowablePlant` that was given one, like a level's seeds.
+    pub species: Option<String>,
+}
+
+pub struct SavedBrick {
+    pub position: Vec2,
+    pub dimensions: Vec2,
+    pub mover: Option<PathMover>,
+}
+
+pub struct SavedPowerup {
+    pub position: Vec2,
+    pub name: String,
+    pub cost: i32,
+}
+
+impl SaveGame {
+    /// `board` is the peg board and `shop` is the shop, whichever one is currently shown.
+    pub fn from_worlds(board: &World, shop: &World, resources: &Resources) -> Self {
+        let ui_state = resources.get::<UIState>();
+        let level_state = resources.get::<LevelState>();
+        let registry = resources.get::<PowerupRegistry>();
+
+        let mut save = SaveGame {
+            level_path: level_state.level_path.clone(),
+            next_level: level_state.next_level.clone(),
+            aim_preview_bounces: level_state.aim_preview_bounces,
+            gold: ui_state.gold,
+            incoming_gold: ui_state.incoming_gold,
+            multiplier: level_state.multiplier,
+            balls_remaining: level_state.balls_remaining,
+            next_ball_effects: level_state
+                .effects_to_apply_to_next_ball
+                .iter()
+                .map(|id| registry.get(*id).name().to_string())
+                .collect(),
+            pegs: Vec::new(),
+            stems: Vec::new(),
+            bricks: Vec::new(),
+            rails: Vec::new(),
+            bumpers: Vec::new(),
+            compound_bricks: Vec::new(),
+            catchers: Vec::new(),
+            shop_bricks: Vec::new(),
+        };
+
+        let species_registry = resources.get::<PlantSpeciesRegistry>();
+        for (_, (transform, peg, mover, pendulum, plant)) in board
+            .query::<(
+                &Transform,
+                &Peg,
+                Option<&PathMover>,
+                Option<&Pendulum>,
+                Option<&Plant>,
+            )>()
+            .iter()
+        {
+            save.pegs.push(SavedPeg {
+                position: transform.position.xy(),
+                peg_type: peg.peg_type,
+                mover: mover.cloned(),
+                pivot: pendulum.map(|p| p.pivot),
+                species: plant
+                    .and_then(|p| p.species)
+                    .map(|id| species_registry.get(id).name.clone()),
+            });
+        }
+        for (_, stem) in board.query::<&Stem>().iter() {
+            save.stems.push((stem.position, stem.direction));
+        }
+        for (_, (transform, mover)) in board
+            .query::<With<(&Transform, Option<&PathMover>), &Brick>>()
+            .iter()
+        {
+            save.bricks.push(SavedBrick {
+                position: transform.position.xy(),
+                dimensions: transform.scale.xy(),
+                mover: mover.cloned(),
+            });
+        }
+        for (_, rail) in board.query::<&Rail>().iter() {
+            save.rails.push((rail.a, rail.b));
+        }
+        for (_, (transform, bumper)) in board.query::<(&Transform, &Bumper)>().iter() {
+            save.bumpers
+                .push((transform.position.xy(), bumper.points.clone()));
+        }
+        for (_, (transform, brick)) in board.query::<(&Transform, &CompoundBrick)>().iter() {
+            save.compound_bricks
+                .push((transform.position.xy(), brick.parts.clone()));
+        }
+        for (_, (transform, mover)) in board
+            .query::<With<(&Transform, &PathMover), &Catcher>>()
+            .iter()
+        {
+            save.catchers.push((transform.scale.x, mover.clone()));
+        }
+        for (_, (transform, powerup)) in shop.query::<(&Transform, &Powerup)>().iter() {
+            save.shop_bricks.push(SavedPowerup {
+                position: transform.position.xy(),
+                name: registry.get(powerup.id).name().to_string(),
+                cost: powerup.cost,
+            });
+        }
+        save
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut save = SaveGame {
+            level_path: DEFAULT_LEVEL_PATH.to_string(),
+            next_level: None,
+            aim_preview_bounces: 1,
+            gold: 0,
+            incoming_gold: 0,
+            multiplier: 1.0,
+            balls_remaining: None,
+            next_ball_effects: Vec::new(),
+            pegs: Vec::new(),
+            stems: Vec::new(),
+            bricks: Vec::new(),
+            rails: Vec::new(),
+            bumpers: Vec::new(),
+            compound_bricks: Vec::new(),
+            catchers: Vec::new(),
+            shop_bricks: Vec::new(),
+        };
+
+        for (line, tokens) in data_lines(source) {
+            let mover = match tokens.iter().position(|t| *t == "moves") {
+                Some(index) => Some(PathMover::parse(&tokens, index + 1, line)?),
+                None => None,
+            };
+            let pivot = match tokens.iter().position(|t| *t == "pivot") {
+                Some(index) => Some(parse_vec2(&tokens, index + 1, line)?),
+                None => None,
+            };
+            let species = match tokens.iter().position(|t| *t == "species") {
+                Some(index) => Some(parse_token(&tokens, index + 1, line)?),
+                None => None,
+            };
+
+            match tokens[0] {
+                "level" => save.level_path = parse_token(&tokens, 1, line)?,
+                "next_level" => save.next_level = Some(parse_token(&tokens, 1, line)?),
+                "aim_preview_bounces" => save.aim_preview_bounces = parse_token(&tokens, 1, line)?,
+                "gold" => save.gold = parse_token(&tokens, 1, line)?,
+                "incoming_gold" => save.incoming_gold = parse_token(&tokens, 1, line)?,
+                "multiplier" => save.multiplier = parse_token(&tokens, 1, line)?,
+                "balls_remaining" => save.balls_remaining = Some(parse_token(&tokens, 1, line)?),
+                "next_ball" => save.next_ball_effects.push(parse_token(&tokens, 1, line)?),
+                "peg" => {
+                    let name = tokens.get(1).copied().unwrap_or("");
+                    save.pegs.push(SavedPeg {
+                        peg_type: PegType::from_name(name)
+                            .ok_or_else(|| format!("Line {}: unknown peg type {:?}", line, name))?,
+                        position: parse_vec2(&tokens, 2, line)?,
+                        mover,
+                        pivot,
+                        species,
+                    })
+                }
+                "stem" => save
+                    .stems
+                    .push((parse_vec2(&tokens, 1, line)?, parse_vec2(&tokens, 3, line)?)),
+                "brick" => save.bricks.push(SavedBrick {
+                    position: parse_vec2(&tokens, 1, line)?,
+                    dimensions: parse_vec2(&tokens, 3, line)?,
+                    mover,
+                }),
+                "rail" => save
+                    .rails
+                    .push((parse_vec2(&tokens, 1, line)?, parse_vec2(&tokens, 3, line)?)),
+                "bumper" => save.bumpers.push((
+                    parse_vec2(&tokens, 1, line)?,
+                    parse_bumper_points(&tokens, 3, line)?,
+                )),
+                "compound_brick" => save.compound_bricks.push((
+                    parse_vec2(&tokens, 1, line)?,
+                    parse_compound_brick_parts(&tokens, 3, line)?,
+                )),
+                "catcher" => save.catchers.push((
+                    parse_token(&tokens, 1, line)?,
+                    mover.ok_or_else(|| format!("Line {}: a catcher needs a path", line))?,
+                )),
+                "shop_brick" => save.shop_bricks.push(SavedPowerup {
+                    name: parse_token(&tokens, 1, line)?,
+                    position: parse_vec2(&tokens, 2, line)?,
+                    cost: parse_token(&tokens, 4, line)?,
+                }),
+                other => return Err(format!("Line {}: unknown entry {:?}", line, other)),
+            }
+        }
+        Ok(save)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "level {}\naim_preview_bounces {}\ngold {}\nincoming_gold {}\nmultiplier {:?}\n",
+            self.level_path,
+            self.aim_preview_bounces,
+            self.gold,
+            self.incoming_gold,
+            self.multiplier
+        );
+        if let Some(next_level) = &self.next_level {
+            text.push_str(&format!("next_level {}\n", next_level));
+        }
+        if let Some(balls_remaining) = self.balls_remaining {
+            text.push_str(&format!("balls_remaining {}\n", balls_remaining));
+        }
+        for effect in &self.next_ball_effects {
+            text.push_str(&format!("next_ball {}\n", effect));
+        }
+        // `moves` goes last, as a looping path takes every value after it.
+        for peg in &self.pegs {
+            text.push_str(&format!(
+                "peg {} {:?} {:?}{}{}{}\n",
+                peg.peg_type.name(),
+                peg.position.x,
+                peg.position.y,
+                match &peg.species {
+                    Some(species) => format!(" species {}", species),
+                    None => String::new(),
+                },
+                match peg.pivot {
+                    Some(pivot) => format!(" pivot {:?} {:?}", pivot.x, pivot.y),
+                    None => String::new(),
+                },
+                mover_text(&peg.mover)
+            ));
+        }
+        for (position, direction) in &self.stems {
+            text.push_str(&format!(
+                "stem {:?} {:?} {:?} {:?}\n",
+                position.x, position.y, direction.x, direction.y
+            ));
+        }
+        for brick in &self.bricks {
+            text.push_str(&format!(
+                "brick {:?} {:?} {:?} {:?}{}\n",
+                brick.position.x,
+                brick.position.y,
+                brick.dimensions.x,
+                brick.dimensions.y,
+                mover_text(&brick.mover)
+            ));
+        }
+        for (a, b) in &self.rails {
+            text.push_str(&format!("rail {:?} {:?} {:?} {:?}\n", a.x, a.y, b.x, b.y));
+        }
+        for (position, points) in &self.bumpers {
+            text.push_str(&format!("bumper {:?} {:?}", position.x, position.y));
+            for point in points {
+                text.push_str(&format!(" {:?} {:?}", point.x, point.y));
+            }
+            text.push('\n');
+        }
+        for (position, parts) in &self.compound_bricks {
+            text.push_str(&format!("compound_brick {:?} {:?}", position.x, position.y));
+            for (offset, dimensions) in parts {
+                text.push_str(&format!(
+                    " {:?} {:?} {:?} {:?}",
+                    offset.x, offset.y, dimensions.x, dimensions.y
+                ));
+            }
+            text.push('\n');
+        }
+        for (width, mover) in &self.catchers {
+            text.push_str(&format!("catcher {:?} moves {}\n", width, mover.to_text()));
+        }
+        for powerup in &self.shop_bricks {
+            text.push_str(&format!(
+                "shop_brick {} {:?} {:?} {}\n",
+                powerup.name, powerup.position.x, powerup.position.y, powerup.cost
+            ));
+        }
+        text
+    }
+
+    /// Replaces the board and shop contents with the saved ones.
+    /// Rapier bodies for the old entities are cleaned up by `RapierIntegration::update_colliders`.
+    pub fn restore(&self, board: &mut World, shop: &mut World, resources: &mut Resources) {
+        clear_run_entities(board);
+        clear_run_entities(shop);
+
+        for (position, direction) in &self.stems {
+            spawn_stem(board, resources, *position, *direction);
+        }
+        for peg in &self.pegs {
+            let entity = match peg.pivot {
+                Some(pivot) => spawn_pendulum(board, resources, peg.peg_type, pivot, peg.position),
+                None => spawn_peg(board, resources, peg.position, peg.peg_type),
+            };
+            if let Some(mover) = &peg.mover {
+                let _ = board.insert_one(entity, mover.clone());
+            }
+            if let Some(species) = &peg.species {
+                let species_id = resources.get::<PlantSpeciesRegistry>().find(species);
+                if species_id.is_none() {
+                    println!("No plant species named {:?} in {:?}", species, PLANTS_PATH);
+                }
+                if let Ok(mut plant) = board.get::<&mut Plant>(entity) {
+                    plant.species = species_id;
+                }
+            }
+        }
+        for brick in &self.bricks {
+            let entity = spawn_brick(board, resources, brick.position, brick.dimensions);
+            if let Some(mover) = &brick.mover {
+                let _ = board.insert_one(entity, mover.clone());
+            }
+        }
+        for (a, b) in &self.rails {
+            spawn_rail(board, resources, *a, *b);
+        }
+        for (position, points) in &self.bumpers {
+            spawn_bumper(board, resources, *position, points.clone());
+        }
+        for (position, parts) in &self.compound_bricks {
+            spawn_compound_brick(board, resources, *position, parts.clone());
+        }
+        for (width, mover) in &self.catchers {
+            let entity = spawn_catcher(board, resources, *width, mover.period);
+            let _ = board.insert_one(entity, mover.clone());
+        }
+        for saved in &self.shop_bricks {
+            let id = resources.get::<PowerupRegistry>().find(&saved.name);
+            match id {
+                Some(id) => {
+                    let powerup = Powerup {
+                        id,
+                        cost: saved.cost,
+                    };
+                    spawn_brick_with_powerup(shop, resources, saved.position, powerup);
+                }
+                None => println!("Unknown powerup in save: {:?}", saved.name),
+            }
+        }
+
+        {
+            let mut ui_state = resources.get::<UIState>();
+            ui_state.gold = self.gold;
+            ui_state.incoming_gold = self.incoming_gold;
+        }
+    }
+}
+
+fn mover_text(mover: &Option<PathMover>) -> String {
+    match mover {
+        Some(mover) => format!(" moves {}", mover.to_text()),
+        None => String::new(),
+    }
+}
+
+/// Despawns everything a run spawns, leaving cameras, the witch and backgrounds.
+pub fn clear_run_entities(world: &mut World) {
+    let mut to_despawn = Vec::new();
+    for (e, _) in world.query::<&Peg>().iter() {
+        to_despawn.push(e);
+        if let Ok(visuals) = world.get::<&PegVisuals>(e) {
+            to_despawn.push(visuals.shockwave_child);
+        }
+    }
+    for (e, _) in world.query::<&PegGlyph>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Powerup>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&ScreenSpaceUI>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Brick>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&PendulumPivot>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Rail>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Bumper>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&BumperEdge>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&CompoundBrick>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&CompoundBrickPart>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Stem>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Catcher>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&Ball>().iter() {
+        to_despawn.push(e);
+    }
+    for (e, _) in world.query::<&DelayedAction>().iter() {
+        to_despawn.push(e);
+    }
+
+    for e in to_despawn {
+        let _ = world.despawn(e);
+    }
+}
+
+/// Saves the run if no ball is in play and the last shot's pegs have been cleared.
+pub fn save_run(world: &mut World, resources: &mut Resources) {
+    if !resources.get::<LevelState>().ready_to_shoot {
+        return;
+    }
+
+    let level_state = resources.remove::<LevelState>().unwrap();
+    // Hit pegs are despawned a few at a time after each shot. They'd never be collected
+    // if they were loaded again.
+    let board = if level_state.in_shop {
+        &level_state.other_world
+    } else {
+        &*world
+    };
+    if board.query::<&Peg>().iter().any(|(_, peg)| peg.hit) {
+        resources.add(level_state);
+        resources.get::<UIState>().current_text = "Wait for the pegs to clear first!".into();
+        return;
+    }
+
+    let save = if level_state.in_shop {
+        SaveGame::from_worlds(&level_state.other_world, world, resources)
+    } else {
+        SaveGame::from_worlds(world, &level_state.other_world, resources)
+    };
+    resources.add(level_state);
+
+    write_data_file(SAVE_PATH, &save.to_text());
+    resources.get::<UIState>().current_text = "Saved!".into();
+}
+
+/// Loads the run saved by `save_run` if no ball is in play.
+/// Returns false if nothing was loaded.
+pub fn load_run(world: &mut World, resources: &mut Resources) -> bool {
+    if !resources.get::<LevelState>().ready_to_shoot {
+        return false;
+    }
+    if !data_file_exists(SAVE_PATH) {
+        resources.get::<UIState>().current_text = "There's no saved run to load.".into();
+        return false;
+    }
+
+    let save = match SaveGame::parse(&read_data_file(SAVE_PATH, "")) {
+        Ok(save) => save,
+        Err(e) => {
+            println!("Could not load {:?}: {}", SAVE_PATH, e);
+            return false;
+        }
+    };
+
+    let mut level_state = resources.remove::<LevelState>().unwrap();
+    if level_state.in_shop {
+        save.restore(&mut level_state.other_world, world, resources);
+    } else {
+        save.restore(world, &mut level_state.other_world, resources);
+    }
+
+    level_state.level_path = save.level_path.clone();
+    level_state.next_level = save.next_level.clone();
+    level_state.aim_preview_bounces = save.aim_preview_bounces;
+    level_state.multiplier = save.multiplier;
+    level_state.balls_remaining = save.balls_remaining;
+    level_state.round_over = false;
+    level_state.game_over = false;
+    level_state.effects_to_apply_to_next_ball = {
+        let registry = resources.get::<PowerupRegistry>();
+        save.next_ball_effects
+            .iter()
+            .filter_map(|name| registry.find(name))
+            .collect()
+    };
+    level_state.collected_pegs.clear();
+    resources.add(level_state);
+
+    let mut ui_state = resources.get::<UIState>();
+    ui_state.current_text = "Loaded!".into();
+    ui_state.round_summary = None;
+    ui_state.game_over = None;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let save = SaveGame {
+            level_path: "assets/levels/orchard.level".to_string(),
+            next_level: Some(DEFAULT_LEVEL_PATH.to_string()),
+            aim_preview_bounces: 2,
+            gold: 12,
+            incoming_gold: 3,
+            multiplier: 2.0,
+            balls_remaining: Some(4),
+            next_ball_effects: vec!["BigBall".to_string()],
+            pegs: vec![
+                SavedPeg {
+                    position: Vec2::new(1.5, -2.25),
+                    peg_type: PegType::Gold,
+                    mover: None,
+                    pivot: None,
+                    species: None,
+                },
+                SavedPeg {
+                    position: Vec2::new(0.0, 10.0),
+                    peg_type: PegType::Plant,
+                    mover: Some(PathMover {
+                        path: Path::Orbit {
+                            center: Vec2::new(0.0, 5.0),
+                            radius: 5.0,
+                        },
+                        period: 3.0,
+                        t: 0.25,
+                    }),
+                    pivot: Some(Vec2::new(0.0, 20.0)),
+                    species: None,
+                },
+                SavedPeg {
+                    position: Vec2::new(-10.0, 0.0),
+                    peg_type: PegType::GrowablePlant,
+                    mover: Some(PathMover {
+                        path: Path::Loop(vec![Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)]),
+                        period: 2.0,
+                        t: 0.0,
+                    }),
+                    pivot: None,
+                    species: Some("Vine".to_string()),
+                },
+            ],
+            stems: vec![(Vec2::new(1.0, 2.0), Vec2::new(0.0, 1.0))],
+            bricks: vec![SavedBrick {
+                position: Vec2::new(-5.0, 5.0),
+                dimensions: Vec2::new(6.0, 20.0),
+                mover: None,
+            }],
+            rails: vec![(Vec2::new(0.0, 0.0), Vec2::new(10.0, -3.0))],
+            bumpers: vec![(
+                Vec2::new(-30.0, 0.0),
+                vec![Vec2::ZERO, Vec2::new(10.0, 5.0), Vec2::new(0.0, 10.0)],
+            )],
+            compound_bricks: vec![(
+                Vec2::new(3.0, 4.0),
+                vec![(Vec2::ZERO, Vec2::new(20.0, 4.0))],
+            )],
+            catchers: vec![(
+                24.0,
+                PathMover {
+                    path: Path::PingPong {
+                        from: Vec2::new(-88.0, -71.0),
+                        to: Vec2::new(88.0, -71.0),
+                    },
+                    period: 5.0,
+                    t: 0.75,
+                },
+            )],
+            shop_bricks: vec![SavedPowerup {
+                position: Vec2::new(5.0, -10.0),
+                name: "RockStorm".to_string(),
+                cost: 7,
+            }],
+        };
+
+        let parsed = SaveGame::parse(&save.to_text()).unwrap();
+        assert_eq!(parsed.level_path, "assets/levels/orchard.level");
+        assert_eq!(parsed.next_level.as_deref(), Some(DEFAULT_LEVEL_PATH));
+        assert_eq!(parsed.aim_preview_bounces, 2);
+        assert_eq!(parsed.gold, 12);
+        assert_eq!(parsed.incoming_gold, 3);
+        assert_eq!(parsed.multiplier, 2.0);
+        assert_eq!(parsed.balls_remaining, Some(4));
+        assert_eq!(parsed.next_ball_effects, vec!["BigBall".to_string()]);
+
+        assert_eq!(parsed.pegs.len(), 3);
+        assert_eq!(parsed.pegs[0].position, Vec2::new(1.5, -2.25));
+        assert!(parsed.pegs[0].peg_type == PegType::Gold);
+        assert!(parsed.pegs[0].mover.is_none());
+        assert_eq!(parsed.pegs[1].mover.as_ref().unwrap().t, 0.25);
+        assert_eq!(parsed.pegs[1].pivot, Some(Vec2::new(0.0, 20.0)));
+        assert!(parsed.pegs[1].species.is_none());
+        assert_eq!(parsed.pegs[2].species.as_deref(), Some("Vine"));
+        assert_eq!(parsed.pegs[2].position, Vec2::new(-10.0, 0.0));
+
+        assert_eq!(parsed.stems, save.stems);
+        assert_eq!(parsed.bricks[0].dimensions, Vec2::new(6.0, 20.0));
+        assert_eq!(parsed.rails, save.rails);
+        assert_eq!(parsed.bumpers, save.bumpers);
+        assert_eq!(parsed.compound_bricks, save.compound_bricks);
+        assert_eq!(parsed.catchers.len(), 1);
+        assert_eq!(parsed.catchers[0].0, 24.0);
+        assert_eq!(parsed.catchers[0].1.t, 0.75);
+        assert_eq!(parsed.shop_bricks[0].name, "RockStorm");
+        assert_eq!(parsed.shop_bricks[0].cost, 7);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(SaveGame::parse("peg Diamond 0 0").is_err());
+        assert!(SaveGame::parse("catcher 24").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::*;
+
+pub const SETTINGS_PATH: &str = "settings.txt";
+
+/// Player preferences. These don't affect gameplay, so replays ignore them.
+/// Loaded at startup and saved whenever they're changed.
+pub struct Settings {
+    /// Scales screen shake, from 0 (off) to 1.
+    pub screen_shake: f32,
+    /// From 0 to 1.
+    pub master_volume: f32,
+    /// From 0 to 1, before `master_volume`.
+    pub sfx_volume: f32,
+    /// From 0 to 1, before `master_volume`.
+    pub music_volume: f32,
+    pub aim_preview: AimPreviewLength,
+    pub palette: Palette,
+    /// Draws a shape on each peg showing its type.
+    pub peg_glyphs: bool,
+    /// Used by the HUD and menus instead of the window's scale factor.
+    pub ui_scale: Option<f32>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            screen_shake: 1.0,
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 0.7,
+            aim_preview: AimPreviewLength::Normal,
+            palette: Palette::Standard,
+            peg_glyphs: false,
+            ui_scale: None,
+        }
+    }
+
+    /// Falls back to the defaults if there's no settings file yet.
+    pub fn load() -> Self {
+        match Self::parse(&read_data_file(SETTINGS_PATH, "")) {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("Could not load {:?}: {}", SETTINGS_PATH, e);
+                Self::new()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        write_data_file(SETTINGS_PATH, &self.to_text());
+    }
+
+    /// Anything missing from `source` keeps its default.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut settings = Self::new();
+
+        for (line, tokens) in data_lines(source) {
+            match tokens[0] {
+                "screen_shake" => settings.screen_shake = parse_token(&tokens, 1, line)?,
+                "master_volume" => settings.master_volume = parse_token(&tokens, 1, line)?,
+                "sfx_volume" => settings.sfx_volume = parse_token(&tokens, 1, line)?,
+                "music_volume" => settings.music_volume = parse_token(&tokens, 1, line)?,
+                "aim_preview" => {
+                    let name: String = parse_token(&tokens, 1, line)?;
+                    settings.aim_preview = AimPreviewLength::from_name(&name)
+                        .ok_or_else(|| format!("Line {}: unknown aim preview {:?}", line, name))?;
+                }
+                "palette" => {
+                    let name: String = parse_token(&tokens, 1, line)?;
+                    settings.palette = Palette::from_name(&name)
+                        .ok_or_else(|| format!("Line {}: unknown palette {:?}", line, name))?;
+                }
+                "peg_glyphs" => settings.peg_glyphs = parse_token(&tokens, 1, line)?,
+                "ui_scale" => {
+                    settings.ui_scale = match tokens.get(1) {
+                        Some(&"auto") => None,
+                        _ => Some(parse_token(&tokens, 1, line)?),
+                    }
+                }
+                other => return Err(format!("Line {}: unknown setting {:?}", line, other)),
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn to_text(&self) -> String {
+        let ui_scale = match self.ui_scale {
+            Some(scale) => format!("{:?}", scale),
+            None => "auto".to_string(),
+        };
+        [
+            format!("screen_shake {:?}", self.screen_shake),
+            format!("master_volume {:?}", self.master_volume),
+            format!("sfx_volume {:?}", self.sfx_volume),
+            format!("music_volume {:?}", self.music_volume),
+            format!("aim_preview {}", self.aim_preview.name()),
+            format!("palette {}", self.palette.name()),
+            format!("peg_glyphs {}", self.peg_glyphs),
+            format!("ui_scale {}", ui_scale),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+    }
+
+    /// What sound effects are played at.
+    pub fn effective_sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    pub fn effective_music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+}
+
+/// How far the aim preview continues after its last bounce.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AimPreviewLength {
+    Short,
+    Normal,
+    Long,
+}
+
+impl AimPreviewLength {
+    pub fn seconds(self) -> f32 {
+        match self {
+            AimPreviewLength::Short => 0.15,
+            AimPreviewLength::Normal => 0.3,
+            AimPreviewLength::Long => 0.6,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Short" => AimPreviewLength::Short,
+            "Normal" => AimPreviewLength::Normal,
+            "Long" => AimPreviewLength::Long,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AimPreviewLength::Short => "Short",
+            AimPreviewLength::Normal => "Normal",
+            AimPreviewLength::Long => "Long",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            AimPreviewLength::Short => AimPreviewLength::Normal,
+            AimPreviewLength::Normal => AimPreviewLength::Long,
+            AimPreviewLength::Long => AimPreviewLength::Short,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        self.next().next()
+    }
+}
+
+/// The choices offered by the settings menu. `None` follows the window.
+pub const UI_SCALES: [Option<f32>; 5] = [None, Some(1.0), Some(1.5), Some(2.0), Some(3.0)];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut settings = Settings::new();
+        settings.screen_shake = 0.25;
+        settings.master_volume = 0.5;
+        settings.aim_preview = AimPreviewLength::Long;
+        settings.palette = Palette::BlueYellow;
+        settings.peg_glyphs = true;
+        settings.ui_scale = Some(1.5);
+
+        let parsed = Settings::parse(&settings.to_text()).unwrap();
+        assert_eq!(parsed.screen_shake, 0.25);
+        assert_eq!(parsed.master_volume, 0.5);
+        assert_eq!(parsed.aim_preview, AimPreviewLength::Long);
+        assert_eq!(parsed.palette, Palette::BlueYellow);
+        assert!(parsed.peg_glyphs);
+        assert_eq!(parsed.ui_scale, Some(1.5));
+    }
+
+    #[test]
+    fn missing_values_keep_defaults() {
+        let parsed = Settings::parse("ui_scale auto").unwrap();
+        assert_eq!(parsed.ui_scale, None);
+        assert_eq!(parsed.music_volume, Settings::new().music_volume);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Settings::parse("brightness 3").is_err());
+        assert!(Settings::parse("aim_preview forever").is_err());
+        assert!(Settings::parse("palette sepia").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::*;
+
+impl GameAssets {
+    /// Loaded once at startup from the `AssetManifest` and reused by every run.
+    pub fn load(resources: &Resources) -> Self {
+        let new_material = || {
+            resources
+                .get::<AssetStore<Material>>()
+                .add(Material::default())
+        };
+        let new_peg_material = || PegMaterial {
+            base: new_material(),
+            glowing: new_material(),
+            shockwave: new_material(),
+            manifest_colors: [Color::WHITE; 3],
+        };
+
+        let mut game_assets = GameAssets {
+            board_background: new_material(),
+            shop_background: new_material(),
+            witch_material: new_material(),
+            pupil_material: new_material(),
+            stem_material: new_material(),
+            growable_plant_material: new_peg_material(),
+            plant_material: new_peg_material(),
+            gold_material: new_peg_material(),
+            stone_material: new_peg_material(),
+            multiball_material: new_peg_material(),
+            multiplier_material: new_peg_material(),
+            glyph_material: resources.get::<AssetStore<Material>>().add(Material {
+                shader: Shader::UNLIT_TRANSPARENT,
+                base_color: Color::TRANSPARENT,
+                ..Default::default()
+            }),
+            brick_material: new_material(),
+            ball_material: new_material(),
+            plus_one: new_material(),
+            plus_twenty: new_material(),
+            x2: new_material(),
+        };
+        game_assets.apply_manifest(resources);
+        game_assets
+    }
+
+    /// Fills in every material from the `AssetManifest`.
+    /// Materials are changed in place, so this also reloads the manifest while the game runs.
+    pub fn apply_manifest(&mut self, resources: &Resources) {
+        let mut manifest = resources.get::<AssetManifest>();
+
+        for (name, material) in [
+            ("board_background", &self.board_background),
+            ("shop_background", &self.shop_background),
+            ("witch", &self.witch_material),
+            ("pupil", &self.pupil_material),
+            ("stem", &self.stem_material),
+            ("brick", &self.brick_material),
+            ("ball", &self.ball_material),
+            ("plus_one", &self.plus_one),
+            ("plus_twenty", &self.plus_twenty),
+            ("x2", &self.x2),
+        ] {
+            manifest.update_material(resources, name, material);
+        }
+
+        for peg_type in PEG_TYPES {
+            let peg_material = match peg_type {
+                PegType::GrowablePlant => &mut self.growable_plant_material,
+                PegType::Plant => &mut self.plant_material,
+                PegType::Gold => &mut self.gold_material,
+                PegType::Stone => &mut self.stone_material,
+                PegType::MultiBall => &mut self.multiball_material,
+                PegType::Multiplier => &mut self.multiplier_material,
+            };
+            let parts = [
+                ("base", &peg_material.base),
+                ("glowing", &peg_material.glowing),
+                ("shockwave", &peg_material.shockwave),
+            ];
+            let mut manifest_colors = [Color::WHITE; 3];
+            for (i, (part, material)) in parts.into_iter().enumerate() {
+                let name = format!("peg.{}.{}", peg_asset_name(peg_type), part);
+                manifest.update_material(resources, &name, material);
+                if let Some(entry) = manifest.material(&name) {
+                    manifest_colors[i] = entry.color;
+                }
+            }
+            peg_material.manifest_colors = manifest_colors;
+        }
+
+        self.apply_settings(resources);
+    }
+}
+
+fn spawn_camera(world: &mut World) {
+    let child_camera = world.spawn((
+        Transform::new(),
+        Camera {
+            clear_color: Some(Color::BLACK),
+            exposure: Exposure::EV100(6.0),
+            projection_mode: ProjectionMode::Orthographic {
+                height: VIEW_HEIGHT,
+                z_near: -2.0,
+                z_far: 2.0,
+            },
+            ..Default::default()
+        },
+        MainCamera,
+    ));
+    let parent = world.spawn((Transform::new().with_position(Vec3::Z * 2.0),));
+    let _ = world.set_parent(parent, child_camera);
+}
+
+fn spawn_witch(world: &mut World, game_assets: &GameAssets) {
+    let witch = world.spawn((
+        Transform::new()
+            .with_position(shot_origin() + Vec3::Z * 0.2)
+            .with_scale(Vec3::fill(35.0)),
+        game_assets.witch_material.clone(),
+        Mesh::VERTICAL_QUAD,
+    ));
+
+    let witch_pupil_l = world.spawn((
+        Transform::new().with_scale(Vec3::fill(0.07)),
+        game_assets.pupil_material.clone(),
+        Mesh::VERTICAL_QUAD,
+    ));
+    let witch_pupil_r = world.spawn((
+        Transform::new().with_scale(Vec3::fill(0.07)),
+        game_assets.pupil_material.clone(),
+        Mesh::VERTICAL_QUAD,
+    ));
+
+    let witch_pupil_center_l = world.spawn((
+        Transform::new().with_position(Vec3::new(-0.08, -0.14, 0.0)),
+        Eye {
+            radius: 0.03,
+            range: f32::MAX,
+            art: witch_pupil_l,
+            other_eye: None,
+        },
+    ));
+
+    let witch_pupil_center_r = world.spawn((
+        Transform::new().with_position(Vec3::new(0.12, -0.14, 0.0)),
+        Eye {
+            radius: 0.03,
+            range: f32::MAX,
+            art: witch_pupil_r,
+            other_eye: Some(witch_pupil_center_l),
+        },
+    ));
+    world
+        .get::<&mut Eye>(witch_pupil_center_l)
+        .unwrap()
+        .other_eye = Some(witch_pupil_center_r);
+
+    let _ = world.set_parent(witch, witch_pupil_center_l);
+    let _ = world.set_parent(witch, witch_pupil_center_r);
+
+    let _ = world.set_parent(witch_pupil_center_l, witch_pupil_l);
+    let _ = world.set_parent(witch_pupil_center_r, witch_pupil_r);
+}
+
+/// Everything in a board or shop world that isn't part of the level: the camera, witch,
+/// background and physics.
+fn spawn_scenery(world: &mut World, game_assets: &GameAssets, background: &Handle<Material>) {
+    spawn_camera(world);
+    world.spawn((
+        Transform::new().with_scale(Vec3::new(160.0 * 2.0, 160.0, 1.0)),
+        Mesh::VERTICAL_QUAD,
+        background.clone(),
+    ));
+    spawn_witch(world, game_assets);
+    world.spawn((Transform::new(), EyeFocalPoint, MouseFocalPoint));
+    world.spawn((RapierIntegration::new(),));
+}
+
+/// Despawns everything except the UI.
+fn clear_for_new_run(world: &mut World) {
+    let to_despawn: Vec<_> = world
+        .query::<Without<(), &KeepOnNewRun>>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    for e in to_despawn {
+        let _ = world.despawn(e);
+    }
+}
+
+/// Tears down the current run, if there is one, and starts a new one on `level_path`.
+/// `world` can be the board or the shop, and becomes the board.
+/// Assets, `Settings` and the UI are kept.
+pub fn start_new_run(world: &mut World, resources: &mut Resources, level_path: &str) {
+    let mut shop_world = match resources.remove::<LevelState>() {
+        Some(mut level_state) => {
+            if level_state.in_shop {
+                std::mem::swap(world, &mut level_state.other_world);
+            }
+            level_state.other_world
+        }
+        None => World::new(),
+    };
+
+    clear_for_new_run(world);
+    clear_for_new_run(&mut shop_world);
+
+    {
+        let game_assets = resources.get::<GameAssets>();
+        spawn_scenery(world, &game_assets, &game_assets.board_background);
+        spawn_scenery(&mut shop_world, &game_assets, &game_assets.shop_background);
+    }
+
+    let level = Level::load(level_path).unwrap_or_else(|e| {
+        println!("Could not load level: {}", e);
+        Level::parse("").unwrap()
+    });
+
+    resources.add(UIState::new());
+    level.spawn(world, &mut shop_world, resources);
+
+    let mut level_state = LevelState::new(shop_world);
+    level_state.start_level(&level);
+    resources.add(level_state);
+}
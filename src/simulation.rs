@@ -0,0 +1,223 @@
+use crate::*;
+
+/// Runs the peg board without a window, renderer or audio.
+/// Used for balance tools and for checking shots on machines without a display.
+pub struct Simulation {
+    pub world: World,
+    pub resources: Resources,
+    pub tick: u64,
+}
+
+pub struct ShotReport {
+    pub pegs_hit: Vec<PegType>,
+    /// Gold from pegs. A refund from the catcher isn't counted.
+    pub gold_earned: i32,
+    /// The catcher caught a ball and gave the shot back.
+    pub refunded: bool,
+    pub ticks: u64,
+}
+
+pub const SIMULATION_TIME_STEP: f32 = 1.0 / 60.0;
+
+// A ball stuck forever shouldn't hang a balance run.
+const MAX_SHOT_TICKS: u64 = 60 * 60;
+
+impl Simulation {
+    pub fn new(level: &Level, seed: u64) -> Self {
+        let mut world = World::new();
+        let mut resources = Resources::new();
+        resources.add(UIState::new());
+        resources.add(GameRandom::new(seed));
+        resources.add(PowerupRegistry::load());
+        resources.add(Tuning::load());
+        resources.add(PlantSpeciesRegistry::load());
+
+        world.spawn((RapierIntegration::new(),));
+
+        let mut shop_world = World::new();
+        shop_world.spawn((RapierIntegration::new(),));
+
+        level.spawn(&mut world, &mut shop_world, &mut resources);
+        let mut level_state = LevelState::new(shop_world);
+        level_state.start_level(level);
+        resources.add(level_state);
+
+        Self {
+            world,
+            resources,
+            tick: 0,
+        }
+    }
+
+    pub fn step(&mut self) {
+        run_gameplay(&mut self.world, &mut self.resources, SIMULATION_TIME_STEP);
+        temporary::despawn_temporaries(&mut self.world);
+
+        // There's no UI counting incoming gold up, so bank it immediately.
+        let mut ui_state = self.resources.get::<UIState>();
+        ui_state.gold += ui_state.incoming_gold;
+        ui_state.incoming_gold = 0;
+
+        self.tick += 1;
+    }
+
+    pub fn gold(&self) -> i32 {
+        self.resources.get::<UIState>().gold
+    }
+
+    /// Fires a ball and steps until every ball has left the board.
+    /// Moves on to the next board first if the round is over.
+    /// Returns `None` if a shot isn't allowed right now.
+    pub fn fire(&mut self, direction: Vec2) -> Option<ShotReport> {
+        if self.resources.get::<LevelState>().round_over {
+            start_next_board(&mut self.world, &mut self.resources);
+        }
+
+        if !shoot(&mut self.world, &mut self.resources, direction) {
+            return None;
+        }
+
+        let gold_before = self.gold();
+        let start_tick = self.tick;
+        let mut pegs_hit = Vec::new();
+
+        loop {
+            self.step();
+
+            let mut level_state = self.resources.get::<LevelState>();
+            pegs_hit.extend(level_state.peg_hits.drain(..).map(|hit| hit.peg_type));
+
+            if level_state.ready_to_shoot || self.tick - start_tick > MAX_SHOT_TICKS {
+                break;
+            }
+        }
+
+        let refunded = self.resources.get::<LevelState>().shot_refunded;
+        let refund = if refunded { SHOT_COST } else { 0 };
+        Some(ShotReport {
+            pegs_hit,
+            gold_earned: self.gold() - gold_before - refund,
+            refunded,
+            ticks: self.tick - start_tick,
+        })
+    }
+}
+
+/// Handles `--simulate <shots> [--seed <seed>] [--level <path>]`, firing random shots at a level
+/// and printing the results.
+/// Returns false if the argument wasn't passed and the game should run normally.
+pub fn run_simulation_from_command_line() -> bool {
+    let mut args = std::env::args().skip_while(|a| a != "--simulate");
+    if args.next().is_none() {
+        return false;
+    }
+    let shots: usize = args.next().and_then(|a| a.parse().ok()).unwrap_or(10);
+
+    let level = match Level::load(&level_from_command_line()) {
+        Ok(level) => level,
+        Err(e) => {
+            println!("Could not load level: {}", e);
+            return true;
+        }
+    };
+
+    let seed = seed_from_command_line();
+    println!("Seed: {}", seed);
+
+    let mut simulation = Simulation::new(&level, seed);
+    // Aim is seeded separately so the same seed always fires the same shots.
+    let mut random = Random::new_with_seed(seed.wrapping_add(1));
+
+    for i in 0..shots {
+        // Aim somewhere below the witch.
+        let angle = -random.range_f32(0.1..0.9) * std::f32::consts::PI;
+        let direction = Vec2::new(angle.cos(), angle.sin());
+
+        match simulation.fire(direction) {
+            Some(report) => println!(
+                "Shot {}: hit {} pegs, earned {} gold in {} ticks{}. Gold: {}",
+                i,
+                report.pegs_hit.len(),
+                report.gold_earned,
+                report.ticks,
+                if report.refunded { ", refunded" } else { "" },
+                simulation.gold()
+            ),
+            None => {
+                println!("Shot {}: could not shoot. Gold: {}", i, simulation.gold());
+                break;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation(source: &str) -> Simulation {
+        Simulation::new(&Level::parse(source).unwrap(), 1)
+    }
+
+    fn down() -> Vec2 {
+        Vec2::new(0.0, -1.0)
+    }
+
+    #[test]
+    fn hitting_gold_earns_gold() {
+        // Just off center, so the ball glances off instead of resting on top.
+        let mut simulation = simulation("starting_gold 5\npeg Gold 1 0");
+        let report = simulation.fire(down()).unwrap();
+        assert!(report.pegs_hit == vec![PegType::Gold]);
+        assert_eq!(report.gold_earned, 20);
+        assert!(!report.refunded);
+        assert_eq!(simulation.gold(), 5 - SHOT_COST + 20);
+    }
+
+    #[test]
+    fn missing_earns_nothing() {
+        let mut simulation = simulation("starting_gold 5");
+        let report = simulation.fire(down()).unwrap();
+        assert!(report.pegs_hit.is_empty());
+        assert_eq!(report.gold_earned, 0);
+        assert_eq!(simulation.gold(), 5 - SHOT_COST);
+    }
+
+    #[test]
+    fn shooting_needs_gold() {
+        let mut simulation = simulation("starting_gold 0");
+        assert!(simulation.fire(down()).is_none());
+    }
+
+    #[test]
+    fn catcher_refund_is_not_earned_gold() {
+        // Wide enough to cover the board, so the ball can't miss it.
+        let mut simulation = simulation("starting_gold 5\ncatcher 200 5");
+        let report = simulation.fire(down()).unwrap();
+        assert!(report.refunded);
+        assert_eq!(report.gold_earned, 0);
+        assert_eq!(simulation.gold(), 5);
+    }
+
+    #[test]
+    fn last_ball_ends_the_round() {
+        let mut simulation = simulation("starting_gold 5\nballs_per_round 2");
+        simulation.fire(down()).unwrap();
+        assert!(!simulation.resources.get::<LevelState>().round_over);
+        simulation.fire(down()).unwrap();
+        assert!(simulation.resources.get::<LevelState>().round_over);
+    }
+
+    #[test]
+    fn same_seed_same_shots() {
+        let level = Level::load(DEFAULT_LEVEL_PATH).unwrap();
+        let direction = Vec2::new(0.3, -1.0);
+        let mut a = Simulation::new(&level, 7);
+        let mut b = Simulation::new(&level, 7);
+        let (a, b) = (a.fire(direction).unwrap(), b.fire(direction).unwrap());
+        assert_eq!(a.gold_earned, b.gold_earned);
+        assert_eq!(a.ticks, b.ticks);
+        assert_eq!(a.pegs_hit.len(), b.pegs_hit.len());
+    }
+}
@@ -33,9 +33,8 @@ impl DelayedAction {
     }
 }
 
-pub fn run_delayed_actions(world: &mut World, resources: &mut Resources) {
+pub fn run_delayed_actions(world: &mut World, resources: &mut Resources, time_elapsed: f32) {
     let mut to_despawn = Vec::new();
-    let time_elapsed = resources.get::<Time>().fixed_time_step_seconds as f32;
 
     for (e, t) in world.query::<&mut DelayedAction>().iter() {
         t.time -= time_elapsed;
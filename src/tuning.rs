@@ -0,0 +1,77 @@
+use crate::*;
+
+pub const TUNING_PATH: &str = "assets/tuning.txt";
+const DEFAULT_TUNING: &str = include_str!("../assets/tuning.txt");
+
+/// Gameplay values from `assets/tuning.txt`, so balance can be changed without rebuilding.
+pub struct Tuning {
+    pub shot_power: f32,
+    pub gravity: f32,
+    pub plant_segment_length: f32,
+    pub restitution: f32,
+    pub ball_size: f32,
+}
+
+impl Tuning {
+    pub fn new() -> Self {
+        Self {
+            shot_power: 80.0,
+            gravity: GRAVITY,
+            plant_segment_length: 8.0,
+            restitution: 0.7,
+            ball_size: 3.5,
+        }
+    }
+
+    pub fn load() -> Self {
+        match Self::parse(&read_data_file(TUNING_PATH, DEFAULT_TUNING)) {
+            Ok(tuning) => tuning,
+            Err(e) => {
+                println!("Could not load {:?}: {}", TUNING_PATH, e);
+                Self::new()
+            }
+        }
+    }
+
+    /// Anything missing from `source` keeps its default.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut tuning = Self::new();
+
+        for (line, tokens) in data_lines(source) {
+            let value = parse_token(&tokens, 1, line)?;
+            match tokens[0] {
+                "shot_power" => tuning.shot_power = value,
+                "gravity" => tuning.gravity = value,
+                "plant_segment_length" => tuning.plant_segment_length = value,
+                "restitution" => tuning.restitution = value,
+                "ball_size" => tuning.ball_size = value,
+                other => return Err(format!("Line {}: unknown tuning value {:?}", line, other)),
+            }
+        }
+
+        Ok(tuning)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_tuning_parses() {
+        assert!(Tuning::parse(DEFAULT_TUNING).is_ok());
+    }
+
+    #[test]
+    fn missing_values_keep_defaults() {
+        let tuning = Tuning::parse("shot_power 120").unwrap();
+        assert_eq!(tuning.shot_power, 120.0);
+        assert_eq!(tuning.gravity, GRAVITY);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Tuning::parse("wind 3").is_err());
+        assert!(Tuning::parse("gravity down").is_err());
+    }
+}
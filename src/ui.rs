@@ -1,7 +1,7 @@
 use koi3::{koi_graphics_context::FacesToRender, *};
 use kui::*;
 
-use crate::Powerup;
+use crate::{Powerup, Settings};
 
 pub struct UI {
     drawer: kui::Drawer,
@@ -12,12 +12,43 @@ pub struct UI {
     ui_mesh: Handle<Mesh>,
 }
 
+/// Marks the UI's own entities so `start_new_run` leaves them alone.
+pub struct KeepOnNewRun;
+
 pub struct UIState {
     pub current_text: String,
+    /// Describes the peg under the mouse, shown instead of `current_text`.
+    pub hovered_text: Option<String>,
     pub gold: i32,
+    pub win_gold: i32,
     pub incoming_gold: i32,
     pub hacky_remaining_health: i32,
     pub ball_active: bool,
+    pub balls_remaining: Option<usize>,
+    /// Shown in the middle of the screen between rounds.
+    pub round_summary: Option<String>,
+    /// Shown in the middle of the screen once the run is lost.
+    pub game_over: Option<String>,
+    /// The open menu, drawn over everything else.
+    pub menu_text: Option<String>,
+}
+
+impl UIState {
+    pub fn new() -> Self {
+        Self {
+            current_text: "Welcome to my farm!".to_string(),
+            hovered_text: None,
+            gold: 20,
+            win_gold: 1000,
+            incoming_gold: 0,
+            hacky_remaining_health: 0,
+            ball_active: false,
+            balls_remaining: None,
+            round_summary: None,
+            game_over: None,
+            menu_text: None,
+        }
+    }
 }
 
 impl UI {
@@ -32,23 +63,17 @@ impl UI {
                 ..Default::default()
             },
             RenderFlags::USER_INTERFACE,
+            KeepOnNewRun,
         ));
         world.spawn((
             Transform::new(),
             self.ui_mesh.clone(),
             self.ui_material.clone(),
             RenderFlags::USER_INTERFACE,
+            KeepOnNewRun,
         ));
     }
     pub fn new(world: &mut World, resources: &mut Resources) -> Self {
-        resources.add(UIState {
-            current_text: "Welcome to my farm!".to_string(),
-            gold: 20,
-            incoming_gold: 0,
-            hacky_remaining_health: 0,
-            ball_active: false,
-        });
-
         let projection_matrix =
             koi3::projection_matrices::orthographic_gl(-1.0, 1.0, -1.0, 1.0, 0.0, 1.0);
         world.spawn((
@@ -59,6 +84,7 @@ impl UI {
                 ..Default::default()
             },
             RenderFlags::USER_INTERFACE,
+            KeepOnNewRun,
         ));
         let mut meshes = resources.get::<AssetStore<Mesh>>();
         let mut materials = resources.get::<AssetStore<Material>>();
@@ -87,6 +113,7 @@ impl UI {
             ui_mesh.clone(),
             ui_material.clone(),
             RenderFlags::USER_INTERFACE,
+            KeepOnNewRun,
         ));
 
         let mut fonts = kui::Fonts::empty();
@@ -139,7 +166,13 @@ impl UI {
                             ),
                             padding_with_amount(
                                 |_| 20.0,
-                                text(|state: &mut UIState| format!("Gold: {:?}/1000", state.gold)),
+                                text(|state: &mut UIState| match state.balls_remaining {
+                                    Some(balls) => format!(
+                                        "Gold: {:?}/{:?}  Balls: {:?}",
+                                        state.gold, state.win_gold, balls
+                                    ),
+                                    None => format!("Gold: {:?}/{:?}", state.gold, state.win_gold),
+                                }),
                             ),
                         ))),
                     ),
@@ -170,11 +203,49 @@ impl UI {
                                 |_, _, c: &StandardContext<_>| Color::from_srgb_hex(0xECCAC1, 1.0),
                                 |_, c| c.standard_style().rounding,
                             ),
-                            padding(text(|state: &mut UIState| state.current_text.clone())),
+                            padding(text(|state: &mut UIState| {
+                                state
+                                    .hovered_text
+                                    .clone()
+                                    .unwrap_or_else(|| state.current_text.clone())
+                            })),
                         ))),
                     )),
                 ),
                 center(center_text),
+                center(toggle(
+                    |ui_state: &UIState, _| {
+                        ui_state.menu_text.is_some()
+                            || (ui_state.round_summary.is_none() && ui_state.game_over.is_none())
+                    },
+                    empty(),
+                    fit(stack((
+                        rounded_fill(
+                            |_, _, _c: &StandardContext<_>| Color::from_srgb_hex(0xECCAC1, 1.0),
+                            |_, c| c.standard_style().rounding,
+                        ),
+                        padding(text(|state: &mut UIState| {
+                            state
+                                .game_over
+                                .clone()
+                                .or_else(|| state.round_summary.clone())
+                                .unwrap_or_default()
+                        })),
+                    ))),
+                )),
+                center(toggle(
+                    |ui_state: &UIState, _| ui_state.menu_text.is_none(),
+                    empty(),
+                    fit(stack((
+                        rounded_fill(
+                            |_, _, _c: &StandardContext<_>| Color::from_srgb_hex(0xF0CA00, 1.0),
+                            |_, c| c.standard_style().rounding,
+                        ),
+                        padding(text(|state: &mut UIState| {
+                            state.menu_text.clone().unwrap_or_default()
+                        })),
+                    ))),
+                )),
             )),
         );
 
@@ -192,10 +263,13 @@ impl UI {
         let mut ui_state = resources.get::<UIState>();
         let window = resources.get::<kapp::Window>();
         let (window_width, window_height) = window.size();
-        let ui_scale = window.scale();
+        let ui_scale = resources
+            .get::<Settings>()
+            .ui_scale
+            .unwrap_or(window.scale() as f32);
 
-        let width = window_width as f32 / ui_scale as f32;
-        let height = window_height as f32 / ui_scale as f32;
+        let width = window_width as f32 / ui_scale;
+        let height = window_height as f32 / ui_scale;
 
         self.context.standard_style_mut().ui_scale = ui_scale as _;
         self.context.standard_input_mut().view_size = Vec2::new(width, height);